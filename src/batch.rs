@@ -0,0 +1,96 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+};
+
+use crate::errors::Error;
+
+/// Expands the given paths into the list of instance files they denote.
+/// Each path can either be:
+/// - a plain file which is taken as is,
+/// - a directory whose files are (recursively) collected,
+/// - a glob pattern (ie. `benchmarks/AFG/*.tw`).
+pub fn expand<S: AsRef<str>>(paths: &[S]) -> Result<Vec<PathBuf>, Error> {
+    let mut instances = vec![];
+    for path in paths.iter().map(|p| p.as_ref()) {
+        let as_path = Path::new(path);
+        if as_path.is_file() {
+            instances.push(as_path.to_path_buf());
+        } else if as_path.is_dir() {
+            collect_dir(as_path, &mut instances)?;
+        } else {
+            let before = instances.len();
+            // unreadable entries are skipped just like `ls` would
+            for entry in glob::glob(path)?.filter_map(Result::ok) {
+                if entry.is_file() {
+                    instances.push(entry);
+                }
+            }
+            if instances.len() == before {
+                return Err(Error::NoInstance(path.to_string()));
+            }
+        }
+    }
+    Ok(instances)
+}
+
+/// Collects all the (non hidden) files of the given directory in
+/// lexicographic order
+fn collect_dir(dir: &Path, instances: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<PathBuf>, _>>()?;
+    entries.sort();
+
+    for entry in entries {
+        let hidden = entry
+            .file_name()
+            .map(|n| n.to_string_lossy().starts_with('.'))
+            .unwrap_or(false);
+        if hidden {
+            continue;
+        }
+        if entry.is_dir() {
+            collect_dir(&entry, instances)?;
+        } else {
+            instances.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Applies the function f to all the given items using a pool of (at most)
+/// `jobs` worker threads. The results are returned in the order of the items.
+pub fn run_pool<T, R, F>(items: Vec<T>, jobs: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let n = items.len();
+    let todo = Mutex::new(items.into_iter().enumerate());
+    let done = Mutex::new((0..n).map(|_| None).collect::<Vec<Option<R>>>());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, n.max(1)) {
+            scope.spawn(|| loop {
+                let next = todo.lock().expect("poisoned work queue").next();
+                match next {
+                    None => break,
+                    Some((i, item)) => {
+                        let result = f(item);
+                        done.lock().expect("poisoned results")[i] = Some(result);
+                    }
+                }
+            });
+        }
+    });
+
+    done.into_inner()
+        .expect("poisoned results")
+        .into_iter()
+        .map(|r| r.expect("every item is processed"))
+        .collect()
+}
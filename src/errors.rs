@@ -1,21 +1,80 @@
+use std::path::Path;
+
+use crate::timeout::Termination;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("poisoned")]
     Poisoned,
+    #[error("interrupted")]
+    Interrupted,
     #[error("io error {0}")]
     Io(#[from] std::io::Error),
-    #[error("kill failed {0}")]
-    Kill(String),
+    #[error("json error {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid pattern {0}")]
+    Glob(#[from] glob::PatternError),
+    #[error("minizinc not found on PATH")]
+    MinizincNotFound,
+    #[error("minizinc --solvers-json failed: {0}")]
+    SolverList(String),
+    #[error("no instance matches {0}")]
+    NoInstance(String),
+    #[error("unknown solver {0}")]
+    UnknownSolver(String),
+    #[error("solver {solver} does not support {flag}")]
+    Unsupported { solver: String, flag: String },
+    /// Minizinc terminated with an error. When it was killed by a signal, the
+    /// exit code is 128 + the number of that signal (just like in a shell).
+    /// The termination tells how long the run lasted, what it consumed and
+    /// what minizinc wrote last on stderr.
+    #[error("minizinc failed with exit code {exit_code}: {}", termination.stderr_tail)]
+    Solver {
+        exit_code: i32,
+        termination: Termination,
+    },
+    #[error("{file}:{line}:{column}: expected {expected}, found {found}")]
+    Parse {
+        file: String,
+        line: usize,
+        column: usize,
+        expected: String,
+        found: String,
+    },
 }
 
-impl<T> From<std::sync::PoisonError<T>> for Error {
-    fn from(_: std::sync::PoisonError<T>) -> Self {
-        Self::Poisoned
+impl Error {
+    /// Tells what went wrong when minizinc could not be started
+    pub fn starting_minizinc(error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::NotFound => Error::MinizincNotFound,
+            _ => Error::Io(error),
+        }
+    }
+    /// Tells what file a parse error originates from. Other errors are left
+    /// untouched.
+    pub fn in_file<P: AsRef<Path>>(self, path: P) -> Self {
+        match self {
+            Error::Parse {
+                line,
+                column,
+                expected,
+                found,
+                ..
+            } => Error::Parse {
+                file: path.as_ref().display().to_string(),
+                line,
+                column,
+                expected,
+                found,
+            },
+            e => e,
+        }
     }
 }
 
-impl From<killall::Error> for Error {
-    fn from(e: killall::Error) -> Self {
-        Self::Kill(format!("{:?}", e))
+impl<T> From<std::sync::PoisonError<T>> for Error {
+    fn from(_: std::sync::PoisonError<T>) -> Self {
+        Self::Poisoned
     }
 }
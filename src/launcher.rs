@@ -0,0 +1,568 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    marker::PhantomData,
+    os::unix::process::{CommandExt, ExitStatusExt},
+    path::{Path, PathBuf},
+    process::{ChildStdin, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+use crate::{
+    errors::Error,
+    limits::{mentions_cpu, mentions_memory, Limits},
+    problem::Problem,
+    results::Key,
+    solvers::{find_solver, SolverInfo},
+    timeout::{shutdown_requested, timeout, Outcome, Termination},
+};
+
+/// The number of lines of stderr which are kept for each run
+const STDERR_TAIL_LINES: usize = 20;
+
+/// The events which are emitted while minizinc solves an instance. These are
+/// the messages minizinc prints when it runs with `--json-stream`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "lowercase",
+    bound(deserialize = "S: DeserializeOwned")
+)]
+pub enum Event<S> {
+    /// Minizinc has found a new solution after `elapsed` seconds
+    Solution {
+        #[serde(rename = "time", default, deserialize_with = "seconds")]
+        elapsed: f32,
+        #[serde(rename = "output", deserialize_with = "json_output")]
+        solution: S,
+    },
+    /// Minizinc has reached a conclusion about the instance after `elapsed`
+    /// seconds
+    Status {
+        #[serde(rename = "time", default, deserialize_with = "seconds")]
+        elapsed: f32,
+        status: Status,
+    },
+    /// Some statistics about the search (these depend on the solver)
+    Statistics { statistics: Map<String, Value> },
+    /// Minizinc has emitted a warning
+    Warning { message: String },
+    /// Minizinc has encountered an error
+    Error { message: String },
+    /// Any other message we do not care about (comments, checker output...)
+    #[serde(other)]
+    Other,
+}
+
+impl<S: DeserializeOwned> Event<S> {
+    /// Parses one line of the json stream printed by minizinc
+    pub fn from_json(line: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(line)
+    }
+}
+
+/// The conclusion minizinc reaches about some instance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Status {
+    /// All solutions of the instance have been found
+    AllSolutions,
+    /// The last solution found is proven optimal
+    OptimalSolution,
+    /// The instance has no solution
+    Unsatisfiable,
+    /// The objective of the instance is unbounded
+    Unbounded,
+    /// The instance is either unsatisfiable or unbounded
+    UnsatOrUnbounded,
+    /// The search ended without reaching any conclusion
+    Unknown,
+    /// The search ended because of an error
+    Error,
+}
+
+/// Deserializes a time expressed in milliseconds into seconds
+fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    Ok(u64::deserialize(deserializer)? as f32 / 1000.0)
+}
+/// Deserializes a solution from the json section of the solution output
+fn json_output<'de, D, S>(deserializer: D) -> Result<S, D::Error>
+where
+    D: Deserializer<'de>,
+    S: DeserializeOwned,
+{
+    #[derive(Deserialize)]
+    #[serde(bound(deserialize = "S: DeserializeOwned"))]
+    struct Output<S> {
+        json: S,
+    }
+    Ok(Output::<S>::deserialize(deserializer)?.json)
+}
+
+/// This structure lets you configure how minizinc is invoked to solve some
+/// instance of the problem P.
+#[derive(Debug, Clone)]
+pub struct Launcher<P: Problem> {
+    /// The path to the instance file
+    instance: PathBuf,
+    /// The path to a model overriding the one embedded for the problem P
+    model: Option<PathBuf>,
+    /// The id of the solver backend used by minizinc
+    solver: Option<String>,
+    /// The maximum amount of time minizinc is allowed to run
+    time_limit: Duration,
+    /// How long minizinc may take to stop once it has been interrupted
+    grace_period: Duration,
+    /// The memory and cpu time each process of the run may use
+    limits: Limits,
+    /// The number of threads the solver is allowed to use
+    threads: Option<usize>,
+    /// The seed of the solver random number generator
+    seed: Option<u64>,
+    /// Additional flags passed verbatim to minizinc
+    flags: Vec<String>,
+    _problem: PhantomData<P>,
+}
+
+impl<P: Problem> Launcher<P> {
+    /// Creates a launcher to solve the given instance with the embedded model
+    /// during at most 60 seconds
+    pub fn new<I: AsRef<Path>>(instance: I) -> Self {
+        Self {
+            instance: instance.as_ref().to_path_buf(),
+            model: None,
+            solver: None,
+            time_limit: Duration::from_secs(60),
+            grace_period: Duration::from_secs(5),
+            limits: Limits::default(),
+            threads: None,
+            seed: None,
+            flags: vec![],
+            _problem: PhantomData,
+        }
+    }
+    /// Uses the model stored in the given file instead of the embedded one
+    pub fn model<M: AsRef<Path>>(mut self, model: M) -> Self {
+        self.model = Some(model.as_ref().to_path_buf());
+        self
+    }
+    /// Tells minizinc what solver backend to use
+    pub fn solver<S: Into<String>>(mut self, solver: S) -> Self {
+        self.solver = Some(solver.into());
+        self
+    }
+    /// Sets the maximum amount of time minizinc is allowed to run
+    pub fn time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = time_limit;
+        self
+    }
+    /// Sets how long minizinc may take to stop (and print its final results)
+    /// once it has been interrupted at the end of the time limit. When that
+    /// grace period expires, all the processes of the run are killed.
+    pub fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+    /// Sets the maximum size (in bytes) of the address space of each process
+    /// spawned to solve the instance
+    pub fn mem_limit(mut self, bytes: u64) -> Self {
+        self.limits.memory = Some(bytes);
+        self
+    }
+    /// Sets the maximum cpu time each process spawned to solve the instance
+    /// may consume
+    pub fn cpu_limit(mut self, cpu_limit: Duration) -> Self {
+        self.limits.cpu = Some(cpu_limit);
+        self
+    }
+    /// Sets the number of threads the solver is allowed to use (-p)
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+    /// Sets the seed of the solver random number generator (-r)
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+    /// Passes an additional flag to minizinc
+    pub fn flag<S: Into<String>>(mut self, flag: S) -> Self {
+        self.flags.push(flag.into());
+        self
+    }
+
+    /// Makes sure the selected solver exists among the given ones and that it
+    /// supports the flags which were requested.
+    pub fn validate(&self, solvers: &[SolverInfo]) -> Result<(), Error> {
+        find_solver(solvers, self.solver.as_deref())?.check_flags(&self.solver_flags())
+    }
+    /// Returns the key identifying this run in a results file. The instance
+    /// is identified by its canonical path, so that it is the same run however
+    /// the path was typed (ie. `./bench/a.tw` or `bench/a.tw`).
+    pub fn key(&self) -> Key {
+        let instance = fs::canonicalize(&self.instance).unwrap_or_else(|_| self.instance.clone());
+        Key {
+            instance: instance.display().to_string(),
+            problem: P::NAME.to_string(),
+            model: match &self.model {
+                Some(path) => path.display().to_string(),
+                None => format!("{}.mzn", P::NAME),
+            },
+            solver: self.solver.clone().unwrap_or_else(|| "default".to_string()),
+            seed: self.seed,
+            time_limit: self.time_limit.as_secs(),
+            mem_limit: self.limits.memory,
+            cpu_limit: self.limits.cpu.map(|cpu| cpu.as_secs()),
+        }
+    }
+    /// Returns the arguments minizinc is invoked with (except for its input)
+    fn arguments(&self) -> Vec<String> {
+        let mut args = [
+            "--json-stream",
+            "--output-mode",
+            "json",
+            "--intermediate",
+            "--output-time",
+            "--statistics",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect::<Vec<String>>();
+        if let Some(solver) = &self.solver {
+            args.push("--solver".to_string());
+            args.push(solver.clone());
+        }
+        args.extend(self.solver_flags());
+        args
+    }
+    /// Returns the flags which are passed on to the solver
+    fn solver_flags(&self) -> Vec<String> {
+        let mut flags = vec![];
+        if let Some(threads) = self.threads {
+            flags.push("-p".to_string());
+            flags.push(threads.to_string());
+        }
+        if let Some(seed) = self.seed {
+            flags.push("-r".to_string());
+            flags.push(seed.to_string());
+        }
+        flags.extend(self.flags.iter().cloned());
+        flags
+    }
+
+    /// Writes the data and the model which would be given to minizinc to solve
+    /// the instance in the given directory, and returns the command line which
+    /// solves them. The data goes to `<dir>/<bench>/<instance>.dzn` (or to
+    /// `<dir>/<instance>.dzn` when the instance is not in a bench directory)
+    /// and the embedded model to `<dir>/<problem>.mzn` (a custom model is used where
+    /// it is). The command line is the one used by launch, except that the
+    /// model and data files replace the standard input.
+    pub fn dry_run<D: AsRef<Path>>(&self, dir: D) -> Result<String, Error> {
+        let dir = dir.as_ref();
+        let problem = P::load(&self.instance)?;
+
+        let mut dzn = dir.to_path_buf();
+        if let Some(bench) = self.instance.parent().and_then(|p| p.file_name()) {
+            dzn.push(bench);
+        }
+        let file = self
+            .instance
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        dzn.push(format!("{}.dzn", file));
+        if let Some(parent) = dzn.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&dzn, problem.to_minizinc())?;
+
+        let model = match &self.model {
+            Some(path) => path.clone(),
+            None => {
+                let path = dir.join(format!("{}.mzn", P::NAME));
+                fs::write(&path, P::model())?;
+                path
+            }
+        };
+
+        let mut command = vec!["minizinc".to_string()];
+        command.extend(self.arguments());
+        command.push(model.display().to_string());
+        command.push(dzn.display().to_string());
+        Ok(command
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<String>>()
+            .join(" "))
+    }
+    /// This function transforms the instance into a format which is
+    /// understood by minizinc. Then it invokes minizinc to solve that instance
+    /// and makes sure it does not run for longer than the time limit.
+    /// It returns a hook to follow the progress of the run.
+    pub fn launch(self) -> Result<Run<P>, Error> {
+        if shutdown_requested() {
+            return Err(Error::Interrupted);
+        }
+        let key = self.key();
+        let problem = P::load(&self.instance)?;
+        let iname = name(&self.instance);
+        let dzn = problem.to_minizinc();
+        let model = match &self.model {
+            Some(path) => fs::read_to_string(path)?,
+            None => P::model().to_string(),
+        };
+
+        let mut command = Command::new("minizinc");
+        command
+            .args(self.arguments())
+            .arg("--input-from-stdin")
+            .process_group(0)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        self.limits.apply(&mut command);
+        let mut child = command.spawn().map_err(Error::starting_minizinc)?;
+
+        let stdout = child.stdout.take().expect("Failed to take stdout");
+        let stdout = BufReader::new(stdout);
+        let (sender, events) = mpsc::channel();
+        let out_of_memory = Arc::new(AtomicBool::new(false));
+        let cpu_signalled = Arc::new(AtomicBool::new(false));
+        let reader = spawn_output_reader::<P, _>(
+            stdout,
+            sender,
+            Arc::clone(&out_of_memory),
+            Arc::clone(&cpu_signalled),
+        );
+
+        let stderr = child.stderr.take().expect("Failed to take stderr");
+        let stderr = spawn_stderr_reader(BufReader::new(stderr));
+
+        let time_limit = self.time_limit;
+        let grace_period = self.grace_period;
+        let stdin = child.stdin.take().expect("Failed to take stdin");
+        let watchdog = thread::spawn(move || timeout(child, time_limit, grace_period));
+        // minizinc is watched before its input is written: it may well stop
+        // without reading that input, and it must then be reaped all the same
+        let writer = spawn_input_writer(stdin, dzn, model);
+
+        Ok(Run {
+            name: iname,
+            problem,
+            key,
+            events,
+            writer,
+            reader,
+            stderr,
+            watchdog,
+            limits: self.limits,
+            out_of_memory,
+            cpu_signalled,
+        })
+    }
+}
+
+/// A hook to some instance being solved by minizinc
+pub struct Run<P: Problem> {
+    /// The name of the instance being solved
+    name: String,
+    /// The instance being solved
+    problem: P,
+    /// The key identifying this run in a results file
+    key: Key,
+    /// The events emitted by minizinc while solving the instance
+    events: Receiver<Event<P::Solution>>,
+    /// The thread writing the data and the model on the input of minizinc
+    writer: JoinHandle<()>,
+    /// The thread turning the output of minizinc into events
+    reader: JoinHandle<()>,
+    /// The thread collecting the last lines minizinc writes on stderr
+    stderr: JoinHandle<String>,
+    /// The thread making sure minizinc does not exceed its time limit
+    watchdog: JoinHandle<Result<Termination, Error>>,
+    /// The memory and cpu time each process of the run may use
+    limits: Limits,
+    /// Set when minizinc reports a failed allocation
+    out_of_memory: Arc<AtomicBool>,
+    /// Set when minizinc reports a process exceeded its cpu time limit
+    cpu_signalled: Arc<AtomicBool>,
+}
+
+impl<P: Problem> Run<P> {
+    /// Returns the name of the instance being solved
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Returns the instance being solved
+    pub fn problem(&self) -> &P {
+        &self.problem
+    }
+    /// Returns the seed of the solver random number generator
+    pub fn seed(&self) -> Option<u64> {
+        self.key.seed
+    }
+    /// Returns the key identifying this run in a results file
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+    /// Returns an iterator over the events emitted by minizinc. This iterator
+    /// blocks until the next event is available and completes once minizinc
+    /// has terminated (or has been killed).
+    pub fn events(&self) -> mpsc::Iter<'_, Event<P::Solution>> {
+        self.events.iter()
+    }
+    /// Waits until minizinc has terminated or has been killed and tells how
+    /// it ended (along with the last lines it wrote on stderr). This also
+    /// waits until all of its output has been read, so that no event is lost.
+    /// When minizinc failed (for some other reason than exceeding its
+    /// limits), a solver error is returned.
+    pub fn join(self) -> Result<Termination, Error> {
+        let termination = self.watchdog.join().map_err(|_| Error::Poisoned)??;
+        self.writer.join().map_err(|_| Error::Poisoned)?;
+        // once the whole process group is gone, the output reaches its end
+        self.reader.join().map_err(|_| Error::Poisoned)?;
+        let stderr_tail = self.stderr.join().map_err(|_| Error::Poisoned)?;
+
+        let out_of_memory = self.out_of_memory.load(Ordering::SeqCst);
+        let cpu_signalled = self.cpu_signalled.load(Ordering::SeqCst) || mentions_cpu(&stderr_tail);
+        let termination = Termination {
+            stderr_tail,
+            ..self
+                .limits
+                .classify(termination, out_of_memory, cpu_signalled)
+        };
+        match termination.outcome {
+            Outcome::Failed(status) => Err(Error::Solver {
+                exit_code: status
+                    .code()
+                    .or_else(|| status.signal().map(|s| 128 + s))
+                    .unwrap_or(-1),
+                termination,
+            }),
+            _ => Ok(termination),
+        }
+    }
+}
+
+/// Spawns a thread which writes the data and then the model on the input of
+/// minizinc. When minizinc stops before reading all of it, the write fails
+/// (broken pipe). That is no error of its own: the way minizinc ended (and
+/// what it wrote on stderr) tells why it stopped.
+fn spawn_input_writer(mut stdin: ChildStdin, dzn: String, model: String) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let _ = stdin
+            .write_all(dzn.as_bytes())
+            .and_then(|_| stdin.write_all(model.as_bytes()));
+    })
+}
+
+/// Spawns a thread which processes the minizinc output and turns it into
+/// a stream of typed events. It reads the output until its very end, even
+/// after the child has terminated. The out_of_memory flag is set as soon as
+/// an error reports a failed allocation, the cpu_signalled one as soon as an
+/// error reports a process exceeded its cpu time limit.
+fn spawn_output_reader<P, T>(
+    mut stdout: BufReader<T>,
+    sender: Sender<Event<P::Solution>>,
+    out_of_memory: Arc<AtomicBool>,
+    cpu_signalled: Arc<AtomicBool>,
+) -> JoinHandle<()>
+where
+    P: Problem,
+    T: 'static + Send + Read,
+{
+    thread::spawn(move || {
+        let mut buffer = vec![];
+        loop {
+            buffer.clear();
+            match stdout.read_until(b'\n', &mut buffer) {
+                Ok(0) => break,
+                Ok(_) => {}
+                // the output cannot be read any further
+                Err(_) => break,
+            }
+            // a garbled line must not prevent reading the next ones
+            let line = String::from_utf8_lossy(&buffer);
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+
+            let event = Event::from_json(line).unwrap_or_else(|e| Event::Error {
+                message: format!("unexpected minizinc output ({}): {}", e, line),
+            });
+            if let Event::Error { message } = &event {
+                if mentions_memory(message) {
+                    out_of_memory.store(true, Ordering::SeqCst);
+                }
+                if mentions_cpu(message) {
+                    cpu_signalled.store(true, Ordering::SeqCst);
+                }
+            }
+            // the receiver may have hung up, keep draining the output
+            let _ = sender.send(event);
+        }
+    })
+}
+
+/// Spawns a thread which reads whatever minizinc writes on stderr until the
+/// end, and returns the last lines it has read
+fn spawn_stderr_reader<T>(mut stderr: BufReader<T>) -> JoinHandle<String>
+where
+    T: 'static + Send + Read,
+{
+    thread::spawn(move || {
+        let mut tail = VecDeque::with_capacity(STDERR_TAIL_LINES);
+        let mut buffer = vec![];
+        loop {
+            buffer.clear();
+            match stderr.read_until(b'\n', &mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            if tail.len() == STDERR_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(String::from_utf8_lossy(&buffer).trim_end().to_string());
+        }
+        Vec::from(tail).join("\n")
+    })
+}
+
+/// Quotes the given argument (if needed) so that a shell takes it verbatim
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./=:,+@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Returns a short name (`bench/instance`) for the given instance file
+pub fn name<P: AsRef<Path>>(fname: P) -> String {
+    let path = fname.as_ref();
+    let bench = path
+        .parent()
+        .and_then(|x| x.file_name())
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = path
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("{}/{}", bench, name)
+}
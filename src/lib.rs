@@ -1,6 +1,15 @@
+pub mod batch;
 pub mod errors;
+pub mod limits;
 pub mod matrix;
+pub mod parsing;
 pub mod timeout;
 
+pub mod launcher;
+pub mod problem;
+pub mod results;
+pub mod solvers;
+pub mod stats;
+
 pub mod psp;
 pub mod tsptw;
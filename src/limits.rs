@@ -0,0 +1,130 @@
+use std::{
+    io,
+    os::unix::process::{CommandExt, ExitStatusExt},
+    process::{Command, ExitStatus},
+    time::Duration,
+};
+
+use crate::timeout::{Outcome, Termination};
+
+/// The resources which minizinc (and each of the processes it spawns) may
+/// use. These limits are enforced by the kernel through rlimits.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    /// The maximum size (in bytes) of the address space of each process
+    pub memory: Option<u64>,
+    /// The maximum cpu time each process may consume
+    pub cpu: Option<Duration>,
+}
+
+impl Limits {
+    /// Makes the given command subject to these limits. Since rlimits are
+    /// inherited, they also apply to all the processes it spawns.
+    pub fn apply(&self, command: &mut Command) {
+        let memory = self.memory;
+        // the soft limit sends a SIGXCPU, the hard one a SIGKILL a second later
+        let cpu = self.cpu.map(|cpu| cpu.as_secs().max(1));
+        if memory.is_none() && cpu.is_none() {
+            return;
+        }
+        // SAFETY: the closure runs between fork and exec, it only performs
+        // setrlimit calls which are async-signal-safe.
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(bytes) = memory {
+                    set_limit(libc::RLIMIT_AS, bytes, bytes)?;
+                }
+                if let Some(secs) = cpu {
+                    set_limit(libc::RLIMIT_CPU, secs, secs + 1)?;
+                }
+                Ok(())
+            });
+        }
+    }
+    /// Tells whether a run which failed was actually stopped because it
+    /// exceeded one of these limits, and updates its outcome accordingly.
+    /// The out_of_memory flag tells whether minizinc reported a failed
+    /// allocation, the cpu_signalled one whether it reported that the solver
+    /// was stopped for exceeding its cpu time.
+    pub fn classify(
+        &self,
+        termination: Termination,
+        out_of_memory: bool,
+        cpu_signalled: bool,
+    ) -> Termination {
+        let status = match termination.outcome {
+            Outcome::Failed(status) => status,
+            _ => return termination,
+        };
+        let cpu_time = termination.usage.cpu_time();
+        let outcome = if self.cpu_exceeded(status, cpu_time, cpu_signalled) {
+            Outcome::CpuExceeded(status)
+        } else if self.memory_exceeded(status, out_of_memory) {
+            Outcome::MemoryExceeded(status)
+        } else {
+            Outcome::Failed(status)
+        };
+        Termination {
+            outcome,
+            ..termination
+        }
+    }
+    /// A process which exceeds its cpu limit receives a SIGXCPU (and then a
+    /// SIGKILL). The limit applies to each process on its own, hence these
+    /// signals tell more than the cpu time of the whole run. A SIGKILL may
+    /// come from elsewhere (ie. the OOM killer) though: it only counts when the
+    /// run has consumed that much cpu time. When the process is the solver,
+    /// minizinc fails and reports how the solver died.
+    fn cpu_exceeded(&self, status: ExitStatus, cpu_time: Duration, cpu_signalled: bool) -> bool {
+        let limit = match self.cpu {
+            None => return false,
+            Some(limit) => limit,
+        };
+        match status.signal() {
+            Some(libc::SIGXCPU) => true,
+            Some(libc::SIGKILL) => cpu_time >= limit,
+            _ => cpu_signalled,
+        }
+    }
+    /// A process which exceeds its memory limit sees its allocations fail.
+    /// Minizinc reports it as an error, the solvers usually abort or crash.
+    fn memory_exceeded(&self, status: ExitStatus, out_of_memory: bool) -> bool {
+        if self.memory.is_none() {
+            return false;
+        }
+        let crashed = matches!(
+            status.signal(),
+            Some(libc::SIGABRT) | Some(libc::SIGSEGV) | Some(libc::SIGBUS)
+        );
+        out_of_memory || crashed
+    }
+}
+
+/// Tells whether the given error message is about a failed allocation
+pub fn mentions_memory(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("out of memory")
+        || message.contains("bad_alloc")
+        || message.contains("cannot allocate memory")
+}
+
+/// Tells whether the given error message is about a process stopped for
+/// exceeding its cpu time limit
+pub fn mentions_cpu(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("cpu time limit exceeded") || message.contains("sigxcpu")
+}
+
+/// Sets the soft and hard values of the given resource limit
+fn set_limit(resource: libc::__rlimit_resource_t, soft: u64, hard: u64) -> io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    };
+    // SAFETY: limit is a valid rlimit structure which outlives the call
+    if unsafe { libc::setrlimit(resource, &limit) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
@@ -1,196 +1,647 @@
 use std::{
-    io::{BufRead, BufReader, Read, Write},
-    path::PathBuf,
-    process::{Child, Command, Stdio},
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+    sync::atomic::{AtomicI32, Ordering},
     thread,
     time::Duration,
 };
 
-use anyhow::Error;
-use regex::Regex;
+use anyhow::{anyhow, Error};
+use serde_json::Value;
 use structopt::StructOpt;
 
-use mznlaunch::{psp::load_psp, timeout::timeout, tsptw::load_tsptw};
+use mznlaunch::{
+    batch::{expand, run_pool},
+    errors::Error as MznError,
+    launcher::{name, Event, Launcher},
+    problem::Problem,
+    psp::Psp,
+    results::{Point, Record, ResultStore, RunStatus, Trajectory},
+    solvers::{list_solvers, SolverInfo},
+    stats::{Aggregate, Sample, Summary},
+    timeout::{shutdown, shutdown_requested, Outcome, Termination},
+    tsptw::TSPTW,
+};
+
+/// The exit code of the launcher when some run reached its time limit
+const EXIT_TIMED_OUT: i32 = 2;
+/// The exit code of the launcher when some run of minizinc failed
+const EXIT_FAILED: i32 = 3;
+/// The exit code of the launcher when some run exceeded its memory or cpu limit
+const EXIT_LIMIT: i32 = 4;
+/// The exit code of the launcher when some solution does not check out
+const EXIT_INVALID: i32 = 5;
+/// The exit code of the launcher when it was interrupted (SIGINT or SIGTERM)
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// The exit code of the launcher. This is the code of the most severe outcome
+/// among all the runs.
+static EXIT_CODE: AtomicI32 = AtomicI32::new(0);
 
 #[derive(StructOpt)]
 enum Args {
+    /// Solves a travelling salesman with time windows instance
     Tsptw {
-        fname: String,
-        #[structopt(long, short, default_value = "60")]
-        expiry: u64,
+        #[structopt(flatten)]
+        opts: RunOpts,
     },
+    /// Solves a pigment sequencing instance
     Psp {
-        fname: String,
-        #[structopt(long, short, default_value = "60")]
-        expiry: u64,
+        #[structopt(flatten)]
+        opts: RunOpts,
     },
+    /// Lists the solvers available to minizinc
+    Solvers,
+    /// Checks solutions independently of minizinc
+    Check(Check),
 }
 
-fn main() -> Result<(), Error> {
-    let args = Args::from_args();
-    //let tsptw= load_tsptw(&args.fname)?;
-    //println!("{}", tsptw.to_minizinc(&name(&args.fname)));
-    match args {
-        Args::Tsptw { fname, expiry } => {
-            let child = invoke_tsptw_mzn(&fname)?;
-            timeout(child, Duration::from_secs(expiry))?;
+/// The problems whose solutions can be checked
+#[derive(StructOpt)]
+enum Check {
+    /// Checks travelling salesman with time windows tours
+    Tsptw(CheckOpts),
+    /// Checks pigment sequencing production plans
+    Psp(CheckOpts),
+}
+
+/// The options telling what solutions to check
+#[derive(StructOpt)]
+struct CheckOpts {
+    /// The instance file
+    instance: PathBuf,
+    /// A file holding the solutions (json lines or rows printed by the
+    /// launcher). The solutions are read from stdin when it is omitted
+    solutions: Option<PathBuf>,
+}
+
+/// The options controlling how minizinc is run
+#[derive(StructOpt)]
+struct RunOpts {
+    /// The instance files to solve (plain files, directories or globs)
+    #[structopt(required = true)]
+    instances: Vec<String>,
+    /// The number of instances which are solved at the same time
+    #[structopt(long, short, default_value = "1")]
+    jobs: usize,
+    #[structopt(long, short, default_value = "60")]
+    expiry: u64,
+    /// How long (in seconds) minizinc may take to stop once interrupted
+    #[structopt(long, default_value = "5")]
+    grace: u64,
+    /// The memory (in megabytes) each process spawned by minizinc may use
+    #[structopt(long, parse(try_from_str = megabytes))]
+    mem_limit: Option<u64>,
+    /// The cpu time (in seconds) each process spawned by minizinc may consume
+    #[structopt(long)]
+    cpu_limit: Option<u64>,
+    /// A model to use instead of the embedded one
+    #[structopt(long)]
+    model: Option<PathBuf>,
+    /// The id (or tag) of the solver used by minizinc
+    #[structopt(long)]
+    solver: Option<String>,
+    /// The number of threads used by the solver (a number or 'auto')
+    #[structopt(long)]
+    threads: Option<Threads>,
+    /// The seed of the solver random number generator (the base seed when
+    /// the runs are repeated)
+    #[structopt(long)]
+    seed: Option<u64>,
+    /// Solves the instance this many times, each time with a different seed
+    #[structopt(long, default_value = "1", parse(try_from_str = positive))]
+    repeat: u64,
+    /// Performs the repeated runs concurrently rather than one after the other
+    #[structopt(long)]
+    concurrent: bool,
+    /// A file (json lines) where the results of all runs are appended
+    #[structopt(long)]
+    results: Option<PathBuf>,
+    /// Skips the runs which already have a final record in the results file
+    #[structopt(long, requires = "results")]
+    resume: bool,
+    /// A directory where the trajectory of each run is written (one json
+    /// lines file per run with the time, objective and solution of every
+    /// solution found)
+    #[structopt(long)]
+    trajectories: Option<PathBuf>,
+    /// Writes the data and model of each run to this directory and prints the
+    /// minizinc command line solving them, without running minizinc
+    #[structopt(long)]
+    dry_run: Option<PathBuf>,
+    /// Checks every solution (and its objective) independently of minizinc
+    /// and marks those which do not check out in the results
+    #[structopt(long)]
+    verify: bool,
+}
+
+/// The number of threads used by the solver
+#[derive(Debug, Clone, Copy)]
+enum Threads {
+    /// As many threads as there are cpus
+    Auto,
+    /// This exact number of threads
+    Count(usize),
+}
+impl Threads {
+    fn count(self) -> usize {
+        match self {
+            Threads::Auto => num_cpus::get(),
+            Threads::Count(n) => n,
         }
-        Args::Psp { fname, expiry } => {
-            let child = invoke_psp_mzn(&fname)?;
-            timeout(child, Duration::from_secs(expiry))?;
+    }
+}
+impl FromStr for Threads {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "auto" => Ok(Threads::Auto),
+            _ => match s.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(Threads::Count(n)),
+                _ => Err(anyhow!(
+                    "expected a positive number or 'auto', found '{}'",
+                    s
+                )),
+            },
         }
     }
+}
 
-    Ok(())
+/// Parses a number which must not be zero
+fn positive(s: &str) -> Result<u64, Error> {
+    match s.parse::<u64>()? {
+        0 => Err(anyhow!("expected a positive number, found 0")),
+        n => Ok(n),
+    }
+}
+/// Parses a number of megabytes into a number of bytes
+fn megabytes(s: &str) -> Result<u64, Error> {
+    let megabytes = s.parse::<u64>()?;
+    megabytes
+        .checked_mul(1024 * 1024)
+        .ok_or_else(|| anyhow!("{} megabytes do not fit in 64 bits", megabytes))
 }
 
-/// This function transforms the given instance into a format which is
-/// understood by minizinc. Then it invokes minizinc to solve that instance.
-/// It returns a hook to the underlying minizinc process.
-pub fn invoke_psp_mzn(fname: &str) -> Result<Child, Error> {
-    let psp = load_psp(fname)?;
-    let iname = name(fname);
-    let dzn = psp.to_minizinc();
-
-    let mut child = Command::new("minizinc")
-        .arg("--intermediate")
-        .arg("--output-time")
-        //.arg("--parallel")
-        //.arg(num_cpus::get().to_string())
-        .arg("--input-from-stdin")
-        //.arg("psp.mzn")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()?;
-
-    let instance = include_str!("../psp.mzn");
-
-    let mut stdin = child.stdin.take().expect("Failed to take stdin");
-    stdin.write_all(dzn.as_bytes())?;
-    stdin.write_all(instance.as_bytes())?;
-
-    let stdout = child.stdout.take().expect("Failed to take stdout");
-    let stdout = BufReader::new(stdout);
-    spawn_psp_output_logger(iname, stdout);
-
-    Ok(child)
-}
-
-/// This function transforms the given instance into a format which is
-/// understood by minizinc. Then it invokes minizinc to solve that instance.
-/// It returns a hook to the underlying minizinc process.
-pub fn invoke_tsptw_mzn(fname: &str) -> Result<Child, Error> {
-    let tsptw = load_tsptw(fname)?;
-    let iname = name(fname);
-    let dzn = tsptw.to_minizinc();
-
-    let mut child = Command::new("minizinc")
-        .arg("--intermediate")
-        .arg("--output-time")
-        //.arg("--parallel")
-        //.arg(num_cpus::get().to_string())
-        .arg("--input-from-stdin")
-        //.arg("tsptw.mzn")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()?;
-
-    let instance = include_str!("../tsptw.mzn");
-
-    let mut stdin = child.stdin.take().expect("Failed to take stdin");
-    stdin.write_all(dzn.as_bytes())?;
-    stdin.write_all(instance.as_bytes())?;
-
-    let stdout = child.stdout.take().expect("Failed to take stdout");
-    let stdout = BufReader::new(stdout);
-    spawn_tsptw_output_logger(iname, stdout);
-
-    Ok(child)
-}
-
-/// Spawns a thread which processes the minizinc output and formats is nicely
-fn spawn_tsptw_output_logger<T: 'static + Send + Read>(iname: String, stdout: BufReader<T>) {
-    thread::spawn(move || {
-        let mut makespan: f32 = f32::MAX;
-        let mut permutation: String = "-- no solution --".to_string();
-        let mut elapsed: f32 = 0_f32;
-
-        let re_makespan =
-            Regex::new(r"^% makespan: (\d+.\d+)").expect("failed to compile makespan pattern");
-        let re_permutation =
-            Regex::new(r"^% permutation: \[(.*)\]").expect("failed to compile permutation pattern");
-        let re_elapsed =
-            Regex::new(r"^% time elapsed: (\d+.\d+) s").expect("failed to compile elapsed pattern");
-
-        for line in stdout.lines() {
-            let line = line.unwrap();
-
-            if let Some(cap) = re_makespan.captures(&line) {
-                makespan = (&cap[1]).parse::<f32>().unwrap();
-            }
-            if let Some(cap) = re_permutation.captures(&line) {
-                permutation = cap[1].replace(",", "").to_string()
-            }
-            if let Some(cap) = re_elapsed.captures(&line) {
-                elapsed = cap[1].parse::<f32>().unwrap();
-            }
+fn main() {
+    let args = Args::from_args();
+    let result = match args {
+        Args::Tsptw { opts } => solve::<TSPTW>(&opts),
+        Args::Psp { opts } => solve::<Psp>(&opts),
+        Args::Solvers => solvers(),
+        Args::Check(Check::Tsptw(opts)) => check::<TSPTW>(&opts),
+        Args::Check(Check::Psp(opts)) => check::<Psp>(&opts),
+    };
 
-            if line == "----------" {
-                println!(
-                    "{:<10} | {:>10.4} | {:>10.2} | {}",
-                    iname, makespan, elapsed, permutation
-                );
-            }
+    // process::exit does not flush what is still buffered
+    let _ = std::io::stdout().flush();
+    if shutdown_requested() {
+        eprintln!("mznlaunch: interrupted");
+        process::exit(EXIT_INTERRUPTED);
+    }
+    if let Err(e) = result {
+        eprintln!("Error: {:?}", e);
+        process::exit(1);
+    }
+    process::exit(EXIT_CODE.load(Ordering::SeqCst));
+}
+
+/// Solves all the given instances with minizinc and prints a row for each of
+/// the solutions it finds. Up to `jobs` instances are solved at the same
+/// time, each one under its own time limit. Rows are printed as soon as the
+/// solutions are found, one whole line at a time.
+///
+/// On SIGINT or SIGTERM, all the runs in progress are stopped (gracefully
+/// if possible) and the instances which have not been started are skipped.
+fn solve<P: Problem + Clone + Send>(opts: &RunOpts) -> Result<(), Error> {
+    ctrlc::set_handler(shutdown)?;
+    seeds(opts)?;
+    let instances = expand(&opts.instances)?;
+    // a dry run does not need minizinc at all
+    let solvers = match opts.dry_run {
+        Some(_) => vec![],
+        None => list_solvers()?,
+    };
+    let store = match &opts.results {
+        Some(path) => Some(ResultStore::open(path, opts.resume)?),
+        None => None,
+    };
+
+    let results = run_pool(instances, opts.jobs, |instance| {
+        if shutdown_requested() {
+            return Ok(());
         }
+        let result = solve_instance::<P>(opts, &solvers, store.as_ref(), &instance);
+        if let Err(e) = &result {
+            eprintln!("error: {}: {}", instance.display(), e);
+        }
+        result
     });
+
+    // the failures of minizinc are told by the exit code, not the launcher's
+    let failed = results.iter().filter(|r| r.is_err()).count();
+    if failed > 0 {
+        Err(anyhow!(
+            "{} out of {} instances failed",
+            failed,
+            results.len()
+        ))
+    } else {
+        Ok(())
+    }
 }
 
-/// Spawns a thread which processes the minizinc output and formats is nicely
-fn spawn_psp_output_logger<T: 'static + Send + Read>(iname: String, stdout: BufReader<T>) {
-    thread::spawn(move || {
-        let mut total_cost: f32 = f32::MAX;
-        let mut plan: String = "-- no solution --".to_string();
-        let mut elapsed: f32 = 0_f32;
+/// Returns the seeds of the repeated runs of each instance
+fn seeds(opts: &RunOpts) -> Result<RangeInclusive<u64>, Error> {
+    let base_seed = opts.seed.unwrap_or_default();
+    match base_seed.checked_add(opts.repeat - 1) {
+        Some(last) => Ok(base_seed..=last),
+        None => Err(anyhow!(
+            "cannot repeat {} runs from seed {}: the seeds do not fit in 64 bits",
+            opts.repeat,
+            base_seed
+        )),
+    }
+}
+
+/// Solves the given instance with minizinc (possibly several times)
+fn solve_instance<P: Problem + Clone + Send>(
+    opts: &RunOpts,
+    solvers: &[SolverInfo],
+    store: Option<&ResultStore>,
+    instance: &Path,
+) -> Result<(), Error> {
+    let mut launcher = Launcher::<P>::new(instance)
+        .time_limit(Duration::from_secs(opts.expiry))
+        .grace_period(Duration::from_secs(opts.grace));
+    if let Some(bytes) = opts.mem_limit {
+        launcher = launcher.mem_limit(bytes);
+    }
+    if let Some(seconds) = opts.cpu_limit {
+        launcher = launcher.cpu_limit(Duration::from_secs(seconds));
+    }
+    if let Some(model) = &opts.model {
+        launcher = launcher.model(model);
+    }
+    if let Some(solver) = &opts.solver {
+        launcher = launcher.solver(solver);
+    }
+    if let Some(threads) = opts.threads {
+        launcher = launcher.threads(threads.count());
+    }
+    let seeds = seeds(opts)?;
+    if opts.seed.is_some() || opts.repeat > 1 {
+        launcher = launcher.seed(*seeds.start());
+    }
+    if let Some(dir) = &opts.dry_run {
+        if opts.repeat <= 1 {
+            println!("{}", launcher.dry_run(dir)?);
+        } else {
+            for seed in seeds {
+                println!("{}", launcher.clone().seed(seed).dry_run(dir)?);
+            }
+        }
+        return Ok(());
+    }
+    launcher.validate(solvers)?;
+
+    let trajectories = opts.trajectories.as_deref();
+    let verify = opts.verify;
+    if opts.repeat <= 1 {
+        solve_once(launcher, store, trajectories, verify)?;
+        return Ok(());
+    }
 
-        let re_total_cost =
-            Regex::new(r"^% total cost : (\d+.\d+)").expect("failed to compile total cost pattern");
-        let re_plan =
-            Regex::new(r"^% plan       : \[(.*)\]").expect("failed to compile plan pattern");
-        let re_elapsed =
-            Regex::new(r"^% time elapsed: (\d+.\d+) s").expect("failed to compile elapsed pattern");
+    let samples = if opts.concurrent {
+        thread::scope(|scope| {
+            let handles = seeds
+                .map(|seed| {
+                    let launcher = launcher.clone().seed(seed);
+                    scope.spawn(move || solve_once(launcher, store, trajectories, verify))
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("run panicked"))
+                .collect::<Result<Vec<Sample>, Error>>()
+        })?
+    } else {
+        seeds
+            .map(|seed| solve_once(launcher.clone().seed(seed), store, trajectories, verify))
+            .collect::<Result<Vec<Sample>, Error>>()?
+    };
 
-        for line in stdout.lines() {
-            let line = line.unwrap();
+    let summary = Summary::new(name(instance), &samples);
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    print_aggregate(&mut out, &summary, "objective", summary.objective)?;
+    print_aggregate(&mut out, &summary, "time to best", summary.time_to_best)?;
+    writeln!(out, "{}", serde_json::to_string(&summary)?)?;
+    Ok(())
+}
 
-            if let Some(cap) = re_total_cost.captures(&line) {
-                total_cost = (&cap[1]).parse::<f32>().unwrap();
-            }
-            if let Some(cap) = re_plan.captures(&line) {
-                plan = cap[1].replace(",", "").to_string()
+/// Solves the instance once and prints a row for each solution it finds.
+/// When a results store is given, a record is appended to it for each of
+/// these solutions and a final record is appended once the run is over. Runs
+/// which were already completed are not performed again. When a trajectories
+/// directory is given, all the solutions of the run are saved in a file of
+/// that directory. When verifying, each solution is checked independently of
+/// minizinc and the run ends with an error status if any of its solutions
+/// does not check out. A run where minizinc fails ends with an error status too:
+/// only the failures of the launcher itself are returned as errors.
+fn solve_once<P: Problem>(
+    launcher: Launcher<P>,
+    store: Option<&ResultStore>,
+    trajectories: Option<&Path>,
+    verify: bool,
+) -> Result<Sample, Error> {
+    if let Some(record) = store.and_then(|s| s.completed(&launcher.key())) {
+        return Ok(record.sample());
+    }
+
+    let mut trajectory = trajectories.map(|dir| Trajectory::new(dir, &launcher.key()));
+    let run = launcher.launch()?;
+    let iname = run.name().to_string();
+    let seed = run
+        .seed()
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| "-".into());
+
+    let mut record = Record {
+        key: run.key().clone(),
+        is_final: false,
+        status: None,
+        objective: None,
+        elapsed: None,
+        solution: None,
+        outcome: None,
+        wall_time: None,
+        user_time: None,
+        system_time: None,
+        max_rss: None,
+        error: None,
+        stderr: None,
+        violations: None,
+    };
+    let mut best = None;
+    let mut status = None;
+    let mut solutions = 0;
+    let mut invalid = 0;
+    for event in run.events() {
+        match event {
+            Event::Solution { elapsed, solution } => {
+                let row = P::format_row(elapsed, &solution);
+                println!("{:<10} | {:>10} | {}", iname, seed, row);
+                best = Some(row);
+                solutions += 1;
+                if verify {
+                    let violations = run.problem().check(&solution);
+                    for violation in &violations {
+                        eprintln!("error: {}: invalid solution: {}", iname, violation);
+                    }
+                    if !violations.is_empty() {
+                        invalid += 1;
+                        EXIT_CODE.fetch_max(EXIT_INVALID, Ordering::SeqCst);
+                    }
+                    // the violations of all the solutions found so far
+                    record.violations.get_or_insert_with(Vec::new).extend(
+                        violations
+                            .iter()
+                            .map(|v| format!("solution at {:.3} s: {}", elapsed, v)),
+                    );
+                }
+                let objective = P::objective(&solution);
+                let solution = serde_json::to_value(&solution)?;
+                if let Some(trajectory) = &mut trajectory {
+                    trajectory.push(&Point {
+                        time: elapsed,
+                        objective,
+                        solution: solution.clone(),
+                    })?;
+                }
+                record.objective = Some(objective);
+                record.elapsed = Some(elapsed);
+                record.solution = Some(solution);
+                if let Some(store) = store {
+                    store.append(&record)?;
+                }
             }
-            if let Some(cap) = re_elapsed.captures(&line) {
-                elapsed = cap[1].parse::<f32>().unwrap();
+            Event::Status { status: s, .. } => status = Some(s),
+            Event::Warning { message } => eprintln!("warning: {}", message),
+            Event::Error { message } => eprintln!("error: {}", message),
+            _ => {}
+        }
+    }
+    let termination = match run.join() {
+        Ok(termination) => termination,
+        Err(
+            ref error @ MznError::Solver {
+                ref termination, ..
+            },
+        ) => {
+            eprintln!("error: {}: {}", iname, error);
+            print_status(&iname, &seed, RunStatus::Error, termination);
+            EXIT_CODE.fetch_max(EXIT_FAILED, Ordering::SeqCst);
+            record.is_final = true;
+            record.status = Some(RunStatus::Error);
+            record.error = Some(error.to_string());
+            record.set_termination(termination);
+            if let Some(store) = store {
+                store.append(&record)?;
             }
+            return Ok(record.sample());
+        }
+        Err(error) => return Err(error.into()),
+    };
+    println!(
+        "{:<10} | {:>10} | best: {}",
+        iname,
+        seed,
+        best.as_deref().unwrap_or("no solution found")
+    );
+    let mut run_status = RunStatus::new(&termination.outcome, status, best.is_some());
+    if invalid > 0 {
+        run_status = RunStatus::Error;
+        record.error = Some(format!(
+            "{} out of {} solutions do not check out",
+            invalid, solutions
+        ));
+    }
+    print_status(&iname, &seed, run_status, &termination);
+    EXIT_CODE.fetch_max(exit_code(&termination.outcome), Ordering::SeqCst);
 
-            if line == "----------" {
-                println!(
-                    "{:<10} | {:>10.4} | {:>10.2} | {}",
-                    iname, total_cost, elapsed, plan
-                );
-            }
+    // an interrupted run is not over, it must be redone when resuming
+    if let Outcome::Interrupted { .. } = termination.outcome {
+        return Ok(record.sample());
+    }
+    record.is_final = true;
+    record.status = Some(run_status);
+    record.set_termination(&termination);
+    if let Some(store) = store {
+        store.append(&record)?;
+    }
+    Ok(record.sample())
+}
+
+/// Prints the row telling how some run ended and what it consumed
+fn print_status(iname: &str, seed: &str, status: RunStatus, termination: &Termination) {
+    let usage = termination.usage;
+    println!(
+        "{:<10} | {:>10} | {:<9} | {} after {:.2} s (cpu {:.2} s, max rss {} MB)",
+        iname,
+        seed,
+        status,
+        describe(&termination.outcome),
+        termination.wall_time.as_secs_f32(),
+        usage.cpu_time().as_secs_f32(),
+        usage.max_rss / 1024
+    );
+}
+/// Returns a short description of how some run ended
+fn describe(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Completed(_) => "completed".to_string(),
+        Outcome::TimedOut { killed: false } => "timed out (stopped gracefully)".to_string(),
+        Outcome::TimedOut { killed: true } => "timed out (killed)".to_string(),
+        Outcome::Interrupted { killed: false } => "interrupted (stopped gracefully)".to_string(),
+        Outcome::Interrupted { killed: true } => "interrupted (killed)".to_string(),
+        Outcome::Failed(status) => format!("failed ({})", status),
+        Outcome::MemoryExceeded(status) => format!("exceeded its memory limit ({})", status),
+        Outcome::CpuExceeded(status) => format!("exceeded its cpu limit ({})", status),
+    }
+}
+/// Returns the exit code of the launcher corresponding to the given outcome
+fn exit_code(outcome: &Outcome) -> i32 {
+    match outcome {
+        Outcome::Completed(_) => 0,
+        Outcome::TimedOut { .. } => EXIT_TIMED_OUT,
+        Outcome::Interrupted { .. } => EXIT_INTERRUPTED,
+        Outcome::Failed(_) => EXIT_FAILED,
+        Outcome::MemoryExceeded(_) | Outcome::CpuExceeded(_) => EXIT_LIMIT,
+    }
+}
+
+/// Prints one row of the summary table
+fn print_aggregate<W: Write>(
+    out: &mut W,
+    summary: &Summary,
+    what: &str,
+    aggregate: Option<Aggregate>,
+) -> Result<(), Error> {
+    match aggregate {
+        None => writeln!(
+            out,
+            "{:<10} | {:>3}/{:<3} | {:<12} | -- no solution --",
+            summary.instance, summary.solved, summary.runs, what
+        ),
+        Some(a) => writeln!(
+            out,
+            "{:<10} | {:>3}/{:<3} | {:<12} | min {:>10.4} | median {:>10.4} | mean {:>10.4} | max {:>10.4}",
+            summary.instance, summary.solved, summary.runs, what, a.min, a.median, a.mean, a.max
+        ),
+    }?;
+    Ok(())
+}
+
+/// Prints the solvers available to minizinc along with the standard flags
+/// they support
+fn solvers() -> Result<(), Error> {
+    for solver in list_solvers()? {
+        println!(
+            "{:<30} | {:>10} | {}{}",
+            solver.id,
+            solver.version,
+            solver.std_flags.join(" "),
+            if solver.is_default { " (default)" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+/// Checks the solutions found for some instance independently of minizinc.
+/// It prints a row for each of them (with the objective reported by minizinc),
+/// followed by the violations it finds (if any)
+fn check<P: Problem>(opts: &CheckOpts) -> Result<(), Error> {
+    let instance = P::load(&opts.instance)?;
+    let mut checked = 0;
+    let mut invalid = 0;
+    for_each_solution::<P, _>(opts.solutions.as_deref(), |solution| {
+        let violations = instance.check(&solution);
+        checked += 1;
+        if !violations.is_empty() {
+            invalid += 1;
         }
-    });
+        let verdict = if violations.is_empty() {
+            "valid"
+        } else {
+            "INVALID"
+        };
+        println!(
+            "{:>4} | {:<7} | {:>12.4}",
+            checked,
+            verdict,
+            P::objective(&solution)
+        );
+        for violation in &violations {
+            println!("{:>4} | - {}", "", violation);
+        }
+    })?;
+
+    if checked == 0 {
+        Err(anyhow!("no solution to check"))
+    } else if invalid > 0 {
+        Err(anyhow!(
+            "{} out of {} solutions are invalid",
+            invalid,
+            checked
+        ))
+    } else {
+        Ok(())
+    }
 }
 
-pub fn name(fname: &str) -> String {
-    let path = PathBuf::from(fname);
-    let bench = path
-        .parent()
-        .map(|x| x.file_name().unwrap().to_str().unwrap())
-        .unwrap_or_default();
-    let name = path
-        .file_name()
-        .map(|x| x.to_str().unwrap())
-        .unwrap_or_default();
-    format!("{}/{}", bench, name)
+/// Calls f on each solution held in the given file (or read from stdin).
+/// Each line is either a json object (a results record, a trajectory point, a
+/// solution event printed by minizinc or the solution itself) or a row printed
+/// by the launcher. All other lines are ignored. Solutions are processed as
+/// soon as they are read, so that the output of the launcher can be checked
+/// while it runs.
+fn for_each_solution<P, F>(path: Option<&Path>, mut f: F) -> Result<(), Error>
+where
+    P: Problem,
+    F: FnMut(P::Solution),
+{
+    let input: Box<dyn BufRead> = match path {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(BufReader::new(std::io::stdin())),
+    };
+    for line in input.lines() {
+        if let Some(solution) = parse_solution::<P>(&line?) {
+            f(solution);
+        }
+    }
+    Ok(())
+}
+/// Parses the solution held in one line of a solutions file (if any)
+fn parse_solution<P: Problem>(line: &str) -> Option<P::Solution> {
+    if let Ok(value) = serde_json::from_str::<Value>(line) {
+        // the final record of a run repeats its best solution
+        if value.get("final") == Some(&Value::Bool(true)) {
+            return None;
+        }
+        // the line may also hold the solution itself
+        let candidates = [value.get("solution"), value.pointer("/output/json")];
+        return candidates
+            .iter()
+            .flatten()
+            .chain(Some(&&value))
+            .find_map(|candidate| serde_json::from_value((*candidate).clone()).ok());
+    }
+    // the rows of the launcher start with the instance and seed columns
+    let row = line.splitn(3, '|').nth(2)?;
+    P::parse_row(row)
 }
@@ -0,0 +1,86 @@
+use std::{
+    fmt::Display,
+    io::{BufRead, Lines},
+    str::FromStr,
+};
+
+use crate::errors::Error;
+
+/// A token of some instance file along with the (1-based) column where it
+/// starts
+pub type Token<'a> = (usize, &'a str);
+
+/// Returns the whitespace separated tokens of the given line
+pub fn tokens(line: &str) -> impl Iterator<Item = Token<'_>> {
+    let start = line.as_ptr() as usize;
+    line.split_whitespace()
+        .map(move |token| (1 + token.as_ptr() as usize - start, token))
+}
+
+/// A cursor over the lines of an instance file. It keeps track of the current
+/// line number so as to produce meaningful parse errors.
+pub struct Cursor<B> {
+    /// The lines of the instance file
+    lines: Lines<B>,
+    /// The (1-based) number of the last line that was read
+    line: usize,
+}
+
+impl<B: BufRead> Cursor<B> {
+    /// Creates a new cursor positioned before the first line
+    pub fn new(lines: Lines<B>) -> Self {
+        Self { lines, line: 0 }
+    }
+    /// Returns the next line or None when the end of file is reached
+    pub fn next_line(&mut self) -> Result<Option<String>, Error> {
+        match self.lines.next() {
+            None => Ok(None),
+            Some(line) => {
+                self.line += 1;
+                Ok(Some(line?))
+            }
+        }
+    }
+    /// Returns the next line and fails if the end of file is reached
+    pub fn expect_line(&mut self, expected: &str) -> Result<String, Error> {
+        match self.next_line()? {
+            Some(line) => Ok(line),
+            None => Err(self.eof(expected)),
+        }
+    }
+    /// Parses the first token of the next line
+    pub fn expect_value<T: FromStr>(&mut self, expected: &str) -> Result<T, Error> {
+        let line = self.expect_line(expected)?;
+        let first = tokens(&line).next();
+        match first {
+            Some(token) => self.parse(token, expected),
+            None => Err(self.error(1, expected, "an empty line")),
+        }
+    }
+    /// Parses the given token of the current line
+    pub fn parse<T: FromStr>(&self, token: Token, expected: &str) -> Result<T, Error> {
+        let (column, text) = token;
+        text.parse::<T>()
+            .map_err(|_| self.error(column, expected, format!("'{}'", text)))
+    }
+    /// Returns an error located at the given column of the current line
+    pub fn error<E: Display, F: Display>(&self, column: usize, expected: E, found: F) -> Error {
+        Error::Parse {
+            file: "<input>".to_string(),
+            line: self.line,
+            column,
+            expected: expected.to_string(),
+            found: found.to_string(),
+        }
+    }
+    /// Returns an error telling the end of file was reached unexpectedly
+    pub fn eof<E: Display>(&self, expected: E) -> Error {
+        Error::Parse {
+            file: "<input>".to_string(),
+            line: self.line + 1,
+            column: 1,
+            expected: expected.to_string(),
+            found: "end of file".to_string(),
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::errors::Error;
+
+/// This trait captures everything the launcher needs to know about a given
+/// problem in order to have it solved by minizinc. Adding support for a new
+/// problem boils down to implementing this trait.
+pub trait Problem: 'static + Sized {
+    /// The type of the solutions found by minizinc for this problem. These
+    /// are deserialized from the json object which minizinc prints for each
+    /// solution when it runs with `--output-mode json`.
+    type Solution: 'static + Send + DeserializeOwned + Serialize;
+
+    /// The name of the problem (ie. tsptw)
+    const NAME: &'static str;
+
+    /// Loads an instance of the problem from the given file
+    fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error>;
+    /// Transforms this instance into data which is understood by the model
+    fn to_minizinc(&self) -> String;
+    /// Returns the text of the minizinc model used to solve this problem
+    fn model() -> &'static str;
+    /// Returns the objective value of the given solution
+    fn objective(solution: &Self::Solution) -> f64;
+    /// Formats the problem specific columns of one row of the results table
+    fn format_row(elapsed: f32, solution: &Self::Solution) -> String;
+    /// Parses the problem specific columns of one row of the results table
+    /// (this is the converse of format_row). It returns None when the row
+    /// does not hold a solution.
+    fn parse_row(row: &str) -> Option<Self::Solution>;
+    /// Checks the given solution of this instance independently of minizinc.
+    /// It returns a description of everything which is wrong with that
+    /// solution (nothing when the solution is valid).
+    fn check(&self, solution: &Self::Solution) -> Vec<String>;
+}
@@ -1,10 +1,19 @@
 use std::{
+    convert::{TryFrom, TryInto},
+    fmt,
     fs::File,
     io::{BufRead, BufReader, Lines, Read},
     path::Path,
 };
 
-use crate::{errors::Error, matrix::Matrix};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::Error,
+    matrix::Matrix,
+    parsing::{tokens, Cursor},
+    problem::Problem,
+};
 
 #[derive(Debug, Clone)]
 pub struct Psp {
@@ -75,84 +84,393 @@ demands = {};
     }
 }
 
+//-----------------------------------------------------------------------------
+//--- SOLVING -----------------------------------------------------------------
+//-----------------------------------------------------------------------------
+
+/// A production plan found by minizinc
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Solution {
+    /// The total (stocking + changeover) cost of the plan
+    pub total_cost: usize,
+    /// The part of the total cost which is due to stocking items
+    #[serde(rename = "tot_st_cost")]
+    pub stocking_cost: usize,
+    /// The part of the total cost which is due to changeovers
+    #[serde(rename = "tot_co_cost")]
+    pub changeover_cost: usize,
+    /// The item produced at each time step (0-indexed)
+    #[serde(rename = "solution")]
+    pub plan: Vec<isize>,
+}
+
+impl Problem for Psp {
+    type Solution = Solution;
+
+    const NAME: &'static str = "psp";
+
+    fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        load_psp(path)
+    }
+    fn to_minizinc(&self) -> String {
+        self.to_minizinc()
+    }
+    fn model() -> &'static str {
+        include_str!("../psp.mzn")
+    }
+    fn objective(solution: &Solution) -> f64 {
+        solution.total_cost as f64
+    }
+    fn format_row(elapsed: f32, solution: &Solution) -> String {
+        let plan = solution
+            .plan
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+        format!(
+            "{:>10} | {:>10} | {:>10} | {:>10.2} | {}",
+            solution.total_cost, solution.stocking_cost, solution.changeover_cost, elapsed, plan
+        )
+    }
+    fn parse_row(row: &str) -> Option<Solution> {
+        let columns = row.split('|').map(str::trim).collect::<Vec<&str>>();
+        if columns.len() != 5 {
+            return None;
+        }
+        Some(Solution {
+            total_cost: columns[0].parse().ok()?,
+            stocking_cost: columns[1].parse().ok()?,
+            changeover_cost: columns[2].parse().ok()?,
+            plan: columns[4]
+                .split_whitespace()
+                .map(|x| x.parse().ok())
+                .collect::<Option<Vec<isize>>>()?,
+        })
+    }
+    fn check(&self, solution: &Solution) -> Vec<String> {
+        let report = solution.check(self);
+        report.violations.iter().map(|v| v.to_string()).collect()
+    }
+}
+
+//-----------------------------------------------------------------------------
+//--- CHECKING ----------------------------------------------------------------
+//-----------------------------------------------------------------------------
+
+/// A production plan as printed by the model: the (0-indexed) item which is
+/// produced at each time step, or -1 when the machine is idle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan(pub Vec<isize>);
+
+/// Something which is wrong with a production plan
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The plan does not have exactly one step per time step of the horizon
+    Length { expected: usize, found: usize },
+    /// The plan produces an item which does not exist at the given period
+    UnknownItem { period: usize, item: isize },
+    /// Not enough units of the item have been produced by the given period
+    Backlog {
+        item: usize,
+        period: usize,
+        produced: usize,
+        required: usize,
+    },
+    /// The stocking cost reported with the plan is not the actual one
+    StockingCost { reported: usize, actual: isize },
+    /// The changeover cost reported with the plan is not the actual one
+    ChangeoverCost { reported: usize, actual: isize },
+    /// The total cost reported with the plan is not the actual one
+    TotalCost { reported: usize, actual: isize },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Length { expected, found } => {
+                write!(f, "the plan has {} periods instead of {}", found, expected)
+            }
+            Violation::UnknownItem { period, item } => {
+                write!(
+                    f,
+                    "item {} produced at period {} does not exist",
+                    item, period
+                )
+            }
+            Violation::Backlog {
+                item,
+                period,
+                produced,
+                required,
+            } => write!(
+                f,
+                "only {} units of item {} are produced by period {} but {} are required",
+                produced, item, period, required
+            ),
+            Violation::StockingCost { reported, actual } => write!(
+                f,
+                "the stocking cost is {} but {} was reported",
+                actual, reported
+            ),
+            Violation::ChangeoverCost { reported, actual } => write!(
+                f,
+                "the changeover cost is {} but {} was reported",
+                actual, reported
+            ),
+            Violation::TotalCost { reported, actual } => {
+                write!(
+                    f,
+                    "the total cost is {} but {} was reported",
+                    actual, reported
+                )
+            }
+        }
+    }
+}
+
+/// What the checker tells about some production plan
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// The actual stocking cost of the plan. This is only known when all the
+    /// items of the plan exist.
+    pub stocking_cost: Option<isize>,
+    /// The actual changeover cost of the plan. This is only known when all the
+    /// items of the plan exist.
+    pub changeover_cost: Option<isize>,
+    /// Everything which is wrong with the plan
+    pub violations: Vec<Violation>,
+}
+
+impl Report {
+    /// Tells whether the plan is a valid solution of the instance
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+    /// Returns the actual total cost of the plan (when it is known)
+    pub fn total_cost(&self) -> Option<isize> {
+        Some(self.stocking_cost? + self.changeover_cost?)
+    }
+}
+
+impl Psp {
+    /// Checks the given plan. The cumulative production of each item must
+    /// meet its cumulative demand at every period. Each unit which is produced
+    /// before it is required costs its stocking price for each period it
+    /// spends in stock. A changeover cost is paid whenever the machine
+    /// switches from one item to another (idle periods do not count as items).
+    pub fn check(&self, plan: &Plan) -> Report {
+        let plan = &plan.0;
+        let mut violations = vec![];
+        if plan.len() != self.horizon {
+            violations.push(Violation::Length {
+                expected: self.horizon,
+                found: plan.len(),
+            });
+        }
+        for (period, &item) in plan.iter().enumerate() {
+            if item < -1 || item >= self.n_items as isize {
+                violations.push(Violation::UnknownItem { period, item });
+            }
+        }
+        if !violations.is_empty() {
+            return Report {
+                stocking_cost: None,
+                changeover_cost: None,
+                violations,
+            };
+        }
+
+        let mut stocking_cost = 0_isize;
+        for item in 0..self.n_items {
+            let mut produced = 0;
+            let mut required = 0;
+            for (period, &demand) in self.demands[item].iter().enumerate() {
+                if plan[period] == item as isize {
+                    produced += 1;
+                }
+                required += demand;
+                if produced < required {
+                    violations.push(Violation::Backlog {
+                        item,
+                        period,
+                        produced,
+                        required,
+                    });
+                }
+                let stock = produced as isize - required as isize;
+                stocking_cost += self.stocking[item] as isize * stock;
+            }
+        }
+
+        let mut changeover_cost = 0_isize;
+        let mut previous = None;
+        for &item in plan.iter().filter(|&&item| item >= 0) {
+            let item = item as usize;
+            if let Some(previous) = previous {
+                if previous != item {
+                    changeover_cost += self.changeover[(previous, item)] as isize;
+                }
+            }
+            previous = Some(item);
+        }
+
+        Report {
+            stocking_cost: Some(stocking_cost),
+            changeover_cost: Some(changeover_cost),
+            violations,
+        }
+    }
+}
+
+impl Solution {
+    /// Checks this solution of the given instance. Besides the validity of
+    /// the plan, this makes sure the costs reported by minizinc are the
+    /// actual ones.
+    pub fn check(&self, instance: &Psp) -> Report {
+        let mut report = instance.check(&Plan(self.plan.clone()));
+        if let Some(actual) = report.stocking_cost {
+            if actual != self.stocking_cost as isize {
+                report.violations.push(Violation::StockingCost {
+                    reported: self.stocking_cost,
+                    actual,
+                });
+            }
+        }
+        if let Some(actual) = report.changeover_cost {
+            if actual != self.changeover_cost as isize {
+                report.violations.push(Violation::ChangeoverCost {
+                    reported: self.changeover_cost,
+                    actual,
+                });
+            }
+        }
+        if let Some(actual) = report.total_cost() {
+            if actual != self.total_cost as isize {
+                report.violations.push(Violation::TotalCost {
+                    reported: self.total_cost,
+                    actual,
+                });
+            }
+        }
+        report
+    }
+}
+
 //-----------------------------------------------------------------------------
 //--- PARSING -----------------------------------------------------------------
 //-----------------------------------------------------------------------------
 pub fn load_psp<P: AsRef<Path>>(path: P) -> Result<Psp, Error> {
-    Ok(Psp::from(File::open(path)?))
+    let path = path.as_ref();
+    Psp::try_from(File::open(path)?).map_err(|e| e.in_file(path))
 }
 
-impl From<File> for Psp {
-    fn from(file: File) -> Psp {
-        BufReader::new(file).into()
+impl TryFrom<File> for Psp {
+    type Error = Error;
+
+    fn try_from(file: File) -> Result<Psp, Error> {
+        BufReader::new(file).try_into()
     }
 }
-impl<S: Read> From<BufReader<S>> for Psp {
-    fn from(buf: BufReader<S>) -> Psp {
-        buf.lines().into()
+impl<S: Read> TryFrom<BufReader<S>> for Psp {
+    type Error = Error;
+
+    fn try_from(buf: BufReader<S>) -> Result<Psp, Error> {
+        buf.lines().try_into()
     }
 }
-impl<B: BufRead> From<Lines<B>> for Psp {
-    fn from(mut lines: Lines<B>) -> Psp {
-        let horizon = lines.next().unwrap().unwrap().parse::<usize>().unwrap(); // damn you Result !
-        let n_items = lines.next().unwrap().unwrap().parse::<usize>().unwrap(); // damn you Result !
-        let _nb_orders = lines.next().unwrap().unwrap().parse::<usize>().unwrap(); // damn you Result !
+impl<B: BufRead> TryFrom<Lines<B>> for Psp {
+    type Error = Error;
 
-        let _blank = lines.next();
+    fn try_from(lines: Lines<B>) -> Result<Psp, Error> {
+        let mut cursor = Cursor::new(lines);
+        let horizon = cursor.expect_value::<usize>("the horizon")?;
+        let n_items = cursor.expect_value::<usize>("the number of items")?;
+        let _nb_orders = cursor.expect_value::<usize>("the number of orders")?;
+
+        let _blank = cursor.next_line()?;
         let mut changeover = Matrix::new(n_items, n_items, 0);
 
         let mut i = 0;
-        for line in &mut lines {
-            let line = line.unwrap();
-            let line = line.trim();
-            if line.is_empty() {
+        while let Some(line) = cursor.next_line()? {
+            if line.trim().is_empty() {
                 break;
             }
+            if i >= n_items {
+                return Err(cursor.error(1, "a blank line", "an extra changeover row"));
+            }
 
-            let costs = line.split_whitespace();
-            for (other, cost) in costs.enumerate() {
-                changeover[(i, other)] = cost.parse::<usize>().unwrap();
+            let mut count = 0;
+            for (other, token) in tokens(&line).enumerate() {
+                if other >= n_items {
+                    let expected = format!("{} changeover costs", n_items);
+                    return Err(cursor.error(token.0, expected, "an extra cost"));
+                }
+                changeover[(i, other)] = cursor.parse(token, "a changeover cost")?;
+                count += 1;
+            }
+            if count < n_items {
+                let column = 1 + line.trim_end().len();
+                let expected = format!("{} changeover costs", n_items);
+                return Err(cursor.error(column, expected, count));
             }
 
             i += 1;
         }
+        if i < n_items {
+            let expected = format!("{} changeover rows", n_items);
+            return Err(cursor.error(1, expected, i));
+        }
 
-        let stocking = lines
-            .next()
-            .unwrap()
-            .unwrap()
-            .split_whitespace()
-            .map(|x| x.parse::<usize>().unwrap())
-            .collect::<Vec<usize>>();
+        let line = cursor.expect_line("the stocking costs")?;
+        let stocking = tokens(&line)
+            .map(|token| cursor.parse(token, "a stocking cost"))
+            .collect::<Result<Vec<usize>, Error>>()?;
+        if stocking.len() != n_items {
+            let expected = format!("{} stocking costs", n_items);
+            return Err(cursor.error(1, expected, stocking.len()));
+        }
 
-        let _blank = lines.next();
+        let _blank = cursor.next_line()?;
 
         let mut demands = vec![vec![0; horizon]; n_items];
         i = 0;
-        for line in &mut lines {
-            let line = line.unwrap();
-            let line = line.trim();
-
-            if line.is_empty() {
+        while let Some(line) = cursor.next_line()? {
+            if line.trim().is_empty() {
                 break;
             }
+            if i >= n_items {
+                return Err(cursor.error(1, "a blank line", "an extra demands row"));
+            }
 
-            let demands_for_item = line.split_whitespace().map(|n| n.parse::<usize>().unwrap());
-
-            for (period, demand) in demands_for_item.enumerate() {
-                demands[i][period] += demand;
+            let mut count = 0;
+            for (period, token) in tokens(&line).enumerate() {
+                if period >= horizon {
+                    let expected = format!("{} demands", horizon);
+                    return Err(cursor.error(token.0, expected, "an extra demand"));
+                }
+                demands[i][period] += cursor.parse::<usize>(token, "a demand")?;
+                count += 1;
+            }
+            if count < horizon {
+                let column = 1 + line.trim_end().len();
+                let expected = format!("{} demands", horizon);
+                return Err(cursor.error(column, expected, count));
             }
 
             i += 1;
         }
+        if i < n_items {
+            return Err(cursor.eof(format!("{} demands rows", n_items)));
+        }
 
-        Psp {
+        Ok(Psp {
             n_items,
             horizon,
 
             changeover,
             stocking,
             demands,
-        }
+        })
     }
 }
@@ -0,0 +1,358 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    errors::Error,
+    launcher::Status,
+    stats::Sample,
+    timeout::{Outcome, Termination},
+};
+
+/// This identifies one run of an experiment campaign
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Key {
+    /// The path to the instance file
+    pub instance: String,
+    /// The name of the problem (ie. tsptw)
+    pub problem: String,
+    /// The model used to solve the instance
+    pub model: String,
+    /// The solver used by minizinc
+    pub solver: String,
+    /// The seed of the solver random number generator
+    pub seed: Option<u64>,
+    /// The time limit (in seconds)
+    pub time_limit: u64,
+    /// The memory limit (in bytes) of each process, if any
+    #[serde(default)]
+    pub mem_limit: Option<u64>,
+    /// The cpu time limit (in seconds) of each process, if any
+    #[serde(default)]
+    pub cpu_limit: Option<u64>,
+}
+
+impl Key {
+    /// Returns a digest of the whole key (the 64 bits FNV-1a hash of its
+    /// json representation). Unlike the hashers of the standard library, it
+    /// does not change from one build to the next.
+    pub fn digest(&self) -> u64 {
+        let json = serde_json::to_string(self).expect("a key is always serializable");
+        json.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+}
+
+/// How a run ended, as far as the instance is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RunStatus {
+    /// The best solution found is proven optimal
+    Optimal,
+    /// Some solution was found but it is not proven optimal
+    Satisfied,
+    /// The instance is proven to have no solution
+    Unsat,
+    /// The search ended without finding a solution nor proving there is none
+    Unknown,
+    /// The run reached its time (or cpu) limit before finding any solution
+    Timeout,
+    /// Minizinc failed
+    Error,
+}
+
+impl RunStatus {
+    /// Derives the status of a run from how it ended, from the last status
+    /// reported by minizinc (if any) and from whether it found a solution.
+    pub fn new(outcome: &Outcome, status: Option<Status>, solved: bool) -> Self {
+        match status {
+            Some(Status::OptimalSolution) => return RunStatus::Optimal,
+            Some(Status::Unsatisfiable) | Some(Status::UnsatOrUnbounded) => {
+                return RunStatus::Unsat
+            }
+            Some(Status::Error) => return RunStatus::Error,
+            _ => {}
+        }
+        if solved {
+            return RunStatus::Satisfied;
+        }
+        match outcome {
+            Outcome::TimedOut { .. } | Outcome::CpuExceeded(_) => RunStatus::Timeout,
+            Outcome::Failed(_) | Outcome::MemoryExceeded(_) => RunStatus::Error,
+            Outcome::Completed(_) | Outcome::Interrupted { .. } => RunStatus::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            RunStatus::Optimal => "OPTIMAL",
+            RunStatus::Satisfied => "SATISFIED",
+            RunStatus::Unsat => "UNSAT",
+            RunStatus::Unknown => "UNKNOWN",
+            RunStatus::Timeout => "TIMEOUT",
+            RunStatus::Error => "ERROR",
+        };
+        f.pad(text)
+    }
+}
+
+/// How the processes of a run ended (as opposed to the status, which tells
+/// what the run found out about the instance)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ending {
+    /// Minizinc terminated successfully
+    Completed,
+    /// Minizinc reached its time limit
+    TimedOut,
+    /// Minizinc was stopped because the launcher was shutting down
+    Interrupted,
+    /// Minizinc terminated with an error
+    Failed,
+    /// Some process of the run exceeded its memory limit
+    MemoryExceeded,
+    /// Some process of the run exceeded its cpu time limit
+    CpuExceeded,
+}
+
+impl From<&Outcome> for Ending {
+    fn from(outcome: &Outcome) -> Self {
+        match outcome {
+            Outcome::Completed(_) => Ending::Completed,
+            Outcome::TimedOut { .. } => Ending::TimedOut,
+            Outcome::Interrupted { .. } => Ending::Interrupted,
+            Outcome::Failed(_) => Ending::Failed,
+            Outcome::MemoryExceeded(_) => Ending::MemoryExceeded,
+            Outcome::CpuExceeded(_) => Ending::CpuExceeded,
+        }
+    }
+}
+
+/// One line of the results file. Such a record is written for each solution
+/// found during a run, and a final record summarizes the run once it is over.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    #[serde(flatten)]
+    pub key: Key,
+    /// Is this the last record of the run ?
+    #[serde(rename = "final")]
+    pub is_final: bool,
+    /// How the run ended (only known once the run is over)
+    #[serde(default)]
+    pub status: Option<RunStatus>,
+    /// The objective of the best solution found so far (if any)
+    #[serde(default)]
+    pub objective: Option<f64>,
+    /// The time (in seconds) it took to find the best solution so far
+    #[serde(default)]
+    pub elapsed: Option<f32>,
+    /// The best solution found so far (if any)
+    #[serde(default)]
+    pub solution: Option<Value>,
+    /// How the processes of the run ended (only known once the run is over)
+    #[serde(default)]
+    pub outcome: Option<Ending>,
+    /// The wall clock time (in seconds) the run lasted (only known once the
+    /// run is over)
+    #[serde(default)]
+    pub wall_time: Option<f64>,
+    /// The cpu time (in seconds) spent in user mode by the run (only known
+    /// once the run is over)
+    #[serde(default)]
+    pub user_time: Option<f64>,
+    /// The cpu time (in seconds) spent in kernel mode by the run (only known
+    /// once the run is over)
+    #[serde(default)]
+    pub system_time: Option<f64>,
+    /// The peak resident set size (in kilobytes) of the largest process of the
+    /// run (only known once the run is over)
+    #[serde(default)]
+    pub max_rss: Option<u64>,
+    /// Why the run failed (if it did)
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The last lines minizinc wrote on stderr (only known once the run is
+    /// over, and only when it wrote anything)
+    #[serde(default)]
+    pub stderr: Option<String>,
+    /// Everything which is wrong with the solutions found so far according to
+    /// the checks of the launcher (only when solutions are verified). Any violation reveals
+    /// an error in the model or in the data given to minizinc.
+    #[serde(default)]
+    pub violations: Option<Vec<String>>,
+}
+
+impl Record {
+    /// Records how the run ended, how long it lasted, the resources it
+    /// consumed and what minizinc wrote last on stderr
+    pub fn set_termination(&mut self, termination: &Termination) {
+        let usage = &termination.usage;
+        self.outcome = Some(Ending::from(&termination.outcome));
+        self.wall_time = Some(termination.wall_time.as_secs_f64());
+        self.user_time = Some(usage.user_time.as_secs_f64());
+        self.system_time = Some(usage.system_time.as_secs_f64());
+        self.max_rss = Some(usage.max_rss);
+        if !termination.stderr_tail.is_empty() {
+            self.stderr = Some(termination.stderr_tail.clone());
+        }
+    }
+    /// Returns what this record tells about the run
+    pub fn sample(&self) -> Sample {
+        Sample {
+            seed: self.key.seed,
+            objective: self.objective,
+            time_to_best: self.elapsed,
+            status: self.status,
+        }
+    }
+}
+
+/// A results file in the json lines format. Records are only ever appended
+/// to the file so that nothing is lost when a campaign gets interrupted.
+pub struct ResultStore {
+    /// The file where the records are appended
+    file: Mutex<File>,
+    /// The final record of each run that was already completed
+    completed: HashMap<Key, Record>,
+}
+
+impl ResultStore {
+    /// Opens (or creates) the given results file. When resuming, the runs
+    /// which already have a final record in the file are remembered so that
+    /// they need not be redone.
+    pub fn open<P: AsRef<Path>>(path: P, resume: bool) -> Result<Self, Error> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        let mut completed = HashMap::new();
+        if resume {
+            for line in BufReader::new(&file).lines() {
+                // a campaign killed while writing leaves a truncated line
+                if let Ok(record) = serde_json::from_str::<Record>(&line?) {
+                    if record.is_final {
+                        completed.insert(record.key.clone(), record);
+                    }
+                }
+            }
+        }
+
+        // make sure the next record starts on a line of its own
+        let len = file.seek(SeekFrom::End(0))?;
+        if len > 0 {
+            let mut last = [0_u8];
+            file.seek(SeekFrom::Start(len - 1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n")?;
+            }
+        }
+
+        Ok(Self {
+            file: Mutex::new(file),
+            completed,
+        })
+    }
+    /// Returns the final record of the given run if it was already completed
+    pub fn completed(&self, key: &Key) -> Option<&Record> {
+        self.completed.get(key)
+    }
+    /// Appends the given record to the results file
+    pub fn append(&self, record: &Record) -> Result<(), Error> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        let mut file = self.file.lock()?;
+        file.write_all(line.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// One point of the anytime trajectory of a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Point {
+    /// The time (in seconds) when the solution was found
+    pub time: f32,
+    /// The objective value of the solution
+    pub objective: f64,
+    /// The solution itself
+    pub solution: Value,
+}
+
+/// The trajectory of one run: a json lines file with one point for each of
+/// the solutions found during that run, in the order they were found.
+pub struct Trajectory {
+    /// Where the trajectory is written
+    path: PathBuf,
+    /// The trajectory file (only created once the first point is known)
+    file: Option<BufWriter<File>>,
+}
+
+impl Trajectory {
+    /// Prepares the trajectory of the given run in the given directory. The
+    /// file is only created with the first point of the trajectory, at which
+    /// point any previous trajectory of that same run is overwritten.
+    pub fn new<P: AsRef<Path>>(dir: P, key: &Key) -> Self {
+        Self {
+            path: Self::path(dir, key),
+            file: None,
+        }
+    }
+    /// Returns the path of the trajectory file of the given run. It is named
+    /// `<bench>/<instance>-<solver>[-<seed>]-<hash>.jsonl` after that run,
+    /// where the hash tells apart the runs which differ in some other part of
+    /// their key (model, time limit, memory or cpu limit).
+    pub fn path<P: AsRef<Path>>(dir: P, key: &Key) -> PathBuf {
+        let instance = Path::new(&key.instance);
+        let bench = instance
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let name = instance
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let seed = key.seed.map(|s| format!("-{}", s)).unwrap_or_default();
+        let solver = key.solver.replace('/', "_");
+
+        dir.as_ref().join(bench).join(format!(
+            "{}-{}{}-{:08x}.jsonl",
+            name,
+            solver,
+            seed,
+            key.digest() as u32
+        ))
+    }
+    /// Appends a point to the trajectory
+    pub fn push(&mut self, point: &Point) -> Result<(), Error> {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                if let Some(parent) = self.path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                self.file.insert(BufWriter::new(File::create(&self.path)?))
+            }
+        };
+        serde_json::to_writer(&mut *file, point)?;
+        file.write_all(b"\n")?;
+        // the trajectory of an interrupted campaign must still be usable
+        file.flush()?;
+        Ok(())
+    }
+}
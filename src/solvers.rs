@@ -0,0 +1,104 @@
+use std::process::Command;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::errors::Error;
+
+/// The standard flags a solver may (or may not) support. Minizinc refuses to
+/// pass these flags to a solver which does not declare it supports them.
+pub const STD_FLAGS: [&str; 8] = ["-a", "-f", "-i", "-n", "-p", "-r", "-s", "-t"];
+
+/// The description of a solver backend, as reported by
+/// `minizinc --solvers-json`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolverInfo {
+    /// The unique identifier of the solver (ie. org.gecode.gecode)
+    pub id: String,
+    /// The human readable name of the solver
+    pub name: String,
+    /// The version of the solver
+    pub version: String,
+    /// Alternative names that can be used to select the solver
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The standard flags (-p, -r, -f, ...) supported by the solver
+    #[serde(default)]
+    pub std_flags: Vec<String>,
+    /// Is this the solver minizinc uses when none is specified ? Minizinc
+    /// tells it in the extra info of the solver.
+    #[serde(rename = "extraInfo", default, deserialize_with = "is_default")]
+    pub is_default: bool,
+}
+
+/// Deserializes the isDefault flag out of the extra info of a solver
+fn is_default<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ExtraInfo {
+        #[serde(default)]
+        is_default: bool,
+    }
+    Ok(ExtraInfo::deserialize(deserializer)?.is_default)
+}
+
+impl SolverInfo {
+    /// Returns true iff minizinc selects this solver when invoked with
+    /// `--solver <id>`
+    pub fn matches(&self, id: &str) -> bool {
+        self.id == id
+            || self.id.rsplit('.').next() == Some(id)
+            || self.tags.iter().any(|tag| tag == id)
+    }
+    /// Returns true iff this solver supports the given standard flag
+    pub fn supports(&self, flag: &str) -> bool {
+        self.std_flags.iter().any(|f| f == flag)
+    }
+    /// Makes sure this solver supports all the standard flags among the given
+    /// ones. Flags which are not standard are left for minizinc to check.
+    pub fn check_flags<S: AsRef<str>>(&self, flags: &[S]) -> Result<(), Error> {
+        for flag in flags.iter().map(|f| f.as_ref()) {
+            if STD_FLAGS.contains(&flag) && !self.supports(flag) {
+                return Err(Error::Unsupported {
+                    solver: self.id.clone(),
+                    flag: flag.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Asks minizinc what solvers are available
+pub fn list_solvers() -> Result<Vec<SolverInfo>, Error> {
+    let output = Command::new("minizinc")
+        .arg("--solvers-json")
+        .output()
+        .map_err(Error::starting_minizinc)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = match stderr.trim() {
+            "" => output.status.to_string(),
+            stderr => stderr.to_string(),
+        };
+        return Err(Error::SolverList(message));
+    }
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| Error::SolverList(format!("unexpected output ({})", e)))
+}
+
+/// Finds the solver minizinc uses when invoked with `--solver <id>` or the
+/// default solver when no id is given.
+pub fn find_solver<'a>(
+    solvers: &'a [SolverInfo],
+    id: Option<&str>,
+) -> Result<&'a SolverInfo, Error> {
+    let found = match id {
+        Some(id) => solvers.iter().find(|s| s.matches(id)),
+        None => solvers
+            .iter()
+            .find(|s| s.is_default)
+            .or_else(|| solvers.first()),
+    };
+    found.ok_or_else(|| Error::UnknownSolver(id.unwrap_or("default").to_string()))
+}
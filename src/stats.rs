@@ -0,0 +1,89 @@
+use serde::Serialize;
+
+use crate::results::RunStatus;
+
+/// What one run of minizinc tells about an instance
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// The seed of the solver random number generator
+    pub seed: Option<u64>,
+    /// The objective value of the best solution found (if any)
+    pub objective: Option<f64>,
+    /// The time (in seconds) it took to find the best solution (if any)
+    pub time_to_best: Option<f32>,
+    /// How the run ended (if it is over)
+    pub status: Option<RunStatus>,
+}
+
+/// The minimum, median, mean and maximum of a series of values
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Aggregate {
+    pub min: f64,
+    pub median: f64,
+    pub mean: f64,
+    pub max: f64,
+}
+
+impl Aggregate {
+    /// Aggregates the given values. It returns None when there are no values
+    pub fn of<I: IntoIterator<Item = f64>>(values: I) -> Option<Self> {
+        let mut values = values.into_iter().collect::<Vec<f64>>();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+
+        let n = values.len();
+        let median = if n % 2 == 1 {
+            values[n / 2]
+        } else {
+            (values[n / 2 - 1] + values[n / 2]) / 2.0
+        };
+        Some(Aggregate {
+            min: values[0],
+            median,
+            mean: values.iter().sum::<f64>() / n as f64,
+            max: values[n - 1],
+        })
+    }
+}
+
+/// This summarizes several runs of minizinc on the same instance (typically
+/// with different seeds).
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    /// The name of the instance
+    pub instance: String,
+    /// The number of runs
+    pub runs: usize,
+    /// The number of runs which found at least one solution
+    pub solved: usize,
+    /// The number of runs which proved their best solution optimal
+    pub optimal: usize,
+    /// The seeds used for the runs
+    pub seeds: Vec<u64>,
+    /// Statistics about the objective of the best solutions
+    pub objective: Option<Aggregate>,
+    /// Statistics about the time (in seconds) to find the best solutions
+    pub time_to_best: Option<Aggregate>,
+}
+
+impl Summary {
+    /// Summarizes the given runs of minizinc on some instance
+    pub fn new<S: Into<String>>(instance: S, samples: &[Sample]) -> Self {
+        Summary {
+            instance: instance.into(),
+            runs: samples.len(),
+            solved: samples.iter().filter(|s| s.objective.is_some()).count(),
+            optimal: samples
+                .iter()
+                .filter(|s| s.status == Some(RunStatus::Optimal))
+                .count(),
+            seeds: samples.iter().filter_map(|s| s.seed).collect(),
+            objective: Aggregate::of(samples.iter().filter_map(|s| s.objective)),
+            time_to_best: Aggregate::of(
+                samples.iter().filter_map(|s| s.time_to_best).map(f64::from),
+            ),
+        }
+    }
+}
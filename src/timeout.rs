@@ -1,84 +1,313 @@
 use std::{
-    ops::DerefMut,
-    process::Child,
-    sync::{Arc, Condvar, Mutex},
+    io::ErrorKind,
+    mem,
+    os::unix::process::ExitStatusExt,
+    process::{Child, ExitStatus},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex, Once, Weak,
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use killall::{kill, list_descendants};
-
 use crate::errors::Error;
 
+/// Set once the launcher has been asked to shut down
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+/// The children which are currently being watched
+static ACTIVE: Mutex<Vec<Weak<Shared>>> = Mutex::new(Vec::new());
+/// Makes the launcher the subreaper of its descendants (once)
+static SUBREAPER: Once = Once::new();
+
+/// How a child process ended
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    /// The child terminated successfully
+    Completed(ExitStatus),
+    /// The child reached its time limit. The killed flag tells whether its
+    /// process group had to be killed (it is false when the child stopped
+    /// gracefully after having been interrupted).
+    TimedOut { killed: bool },
+    /// The child was stopped because the launcher was asked to shut down. The
+    /// killed flag has the same meaning as for a timeout.
+    Interrupted { killed: bool },
+    /// The child terminated with an error
+    Failed(ExitStatus),
+    /// The child (or one of its descendants) exceeded its memory limit
+    MemoryExceeded(ExitStatus),
+    /// The child (or one of its descendants) exceeded its cpu time limit
+    CpuExceeded(ExitStatus),
+}
+
+/// What the timeout function tells about the child once it is over
+#[derive(Debug, Clone)]
+pub struct Termination {
+    /// How the child ended
+    pub outcome: Outcome,
+    /// The wall clock time the child has been running
+    pub wall_time: Duration,
+    /// The resources consumed by the child and all the descendants which were
+    /// still in its process group
+    pub usage: Usage,
+    /// The last lines the child wrote on stderr (left empty by the timeout
+    /// function, which does not read the output of the child)
+    pub stderr_tail: String,
+}
+
+/// The resources consumed by some process tree. Only the processes which are
+/// reaped are accounted for: a process which leaves the process group of the
+/// child (ie. with setsid) is not.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Usage {
+    /// The cpu time spent in user mode
+    pub user_time: Duration,
+    /// The cpu time spent in kernel mode
+    pub system_time: Duration,
+    /// The peak resident set size (in kilobytes) of the largest process
+    pub max_rss: u64,
+}
+
+impl Usage {
+    /// Returns the total cpu time (user and system)
+    pub fn cpu_time(&self) -> Duration {
+        self.user_time + self.system_time
+    }
+    /// Adds the usage of some other process tree to this one
+    fn add(&mut self, other: &Usage) {
+        self.user_time += other.user_time;
+        self.system_time += other.system_time;
+        self.max_rss = self.max_rss.max(other.max_rss);
+    }
+}
+
 struct Shared {
-    proc_info: Mutex<(Child, bool)>,
+    /// The moment when the child exited (if it did)
+    exited: Mutex<Option<Instant>>,
     cond_var: Condvar,
 }
 impl Shared {
-    fn new(child: Child) -> Self {
+    fn new() -> Self {
         Self {
-            proc_info: Mutex::new((child, false)),
+            exited: Mutex::new(None),
             cond_var: Condvar::new(),
         }
     }
 }
 
 /// This function does its best to make sure the given child does not run longer
-/// than the given timeout. To do so, it spawns a thread that periodically polls
-/// the child for completion (sucessful or failed: I dont care [though I could]).
-/// If the child process completes, the side thread notifies the main thread
-/// via a cond var.
+/// than the given timeout. To do so, it spawns a thread that blocks until the
+/// child terminates (sucessfully or not) without reaping it. As soon as the
+/// child completes, the side thread notifies the main thread via a cond var.
 ///
 /// On the other hand, the main thread blocks on the condition variable until
 /// either the timeout occurs or it gets notified by the conditional variable.
 ///
-/// In case the timeout occurs, some cleanup is performed to make sure all
-/// children processes are killed.
-pub fn timeout(child: Child, timeout: Duration) -> Result<(), Error> {
-    let shared = Arc::new(Shared::new(child));
+/// The child must lead its own process group (see `CommandExt::process_group`).
+/// In case the timeout occurs, the whole group is first asked to stop with a
+/// SIGINT so that the child gets a chance to flush its final solution and
+/// statistics. If it is still running after the grace period, the group is
+/// killed. In any case, the processes left in the group once the child is over
+/// are killed too, so that no grandchild outlives its ancestor.
+///
+/// The very same shutdown sequence is performed when `shutdown` is called
+/// before the timeout occurs.
+///
+/// The launcher becomes the subreaper of its descendants, so that the orphans
+/// of the child are reparented to it rather than to init. Hence the processes
+/// left in the group can be reaped along with the child, and their usage is
+/// accounted for even when they have been killed.
+pub fn timeout(child: Child, timeout: Duration, grace: Duration) -> Result<Termination, Error> {
+    SUBREAPER.call_once(|| {
+        // SAFETY: prctl has no memory safety implication with these arguments
+        unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) };
+    });
+    let start = Instant::now();
+    let shared = Arc::new(Shared::new());
 
+    let pid = child.id();
     let shared2 = Arc::clone(&shared);
-    thread::spawn(move || loop_until_process_is_finished(shared2));
+    thread::spawn(move || wait_until_process_is_finished(pid, shared2));
+    {
+        let mut active = ACTIVE.lock()?;
+        active.retain(|run| run.strong_count() > 0);
+        active.push(Arc::downgrade(&shared));
+    }
 
     let shared = shared.as_ref();
-    let lock = shared.proc_info.lock()?;
-    let (mut guard, _) = shared
+    let lock = shared.exited.lock()?;
+    let (mut lock, _) = shared
+        .cond_var
+        .wait_timeout_while(lock, timeout, |exited| {
+            exited.is_none() && !shutdown_requested()
+        })?;
+
+    // the child is stopped when it is still running at this point
+    let stopped = lock.is_none();
+    let interrupted = stopped && shutdown_requested();
+    let mut killed = false;
+    if stopped {
+        let reason = if interrupted {
+            "the launcher is shutting down"
+        } else {
+            "reached its time limit"
+        };
+        eprintln!("mznlaunch: pid {} {}, sending SIGINT", pid, reason);
+        signal_group(pid, libc::SIGINT)?;
+
+        let (next_lock, wait) = shared
+            .cond_var
+            .wait_timeout_while(lock, grace, |exited| exited.is_none())?;
+        lock = next_lock;
+        if wait.timed_out() {
+            eprintln!(
+                "mznlaunch: pid {} still running after {:?}, sending SIGKILL",
+                pid, grace
+            );
+            signal_group(pid, libc::SIGKILL)?;
+            eprintln!("mznlaunch: process group {} killed", pid);
+            killed = true;
+        } else {
+            eprintln!("mznlaunch: pid {} stopped gracefully", pid);
+        }
+    }
+
+    // The child must not be reaped before the side thread has seen it exit.
+    // Otherwise, its pid could be recycled under the feet of that thread.
+    let lock = shared
         .cond_var
-        .wait_timeout_while(lock, timeout, |&mut (_, done)| !done)?;
+        .wait_while(lock, |exited| exited.is_none())?;
+    let exited = lock.unwrap_or_else(Instant::now);
+    drop(lock);
 
-    let (child, done) = guard.deref_mut();
-    *done = true;
-    maybe_cleanup(child)?;
+    // Until it is reaped, the child keeps its pid (hence the group id) from
+    // being reused. So this can only hit the processes it left behind.
+    signal_group(pid, libc::SIGKILL)?;
 
-    Ok(())
+    let (status, mut usage) = reap(child.id() as libc::pid_t)?;
+    // the processes left in the group are orphans by now, hence our children
+    while let Some((_, orphan)) = reap_group(pid)? {
+        usage.add(&orphan);
+    }
+    let wall_time = exited.duration_since(start);
+
+    let outcome = if interrupted {
+        Outcome::Interrupted { killed }
+    } else if stopped {
+        Outcome::TimedOut { killed }
+    } else if status.success() {
+        Outcome::Completed(status)
+    } else {
+        Outcome::Failed(status)
+    };
+    Ok(Termination {
+        outcome,
+        wall_time,
+        usage,
+        stderr_tail: String::new(),
+    })
 }
 
-/// Loops until the process finishes and signals it through the conditional var
-fn loop_until_process_is_finished(shared: Arc<Shared>) -> Result<(), Error> {
-    loop {
-        {
-            let shared = shared.as_ref();
-            let (ref mut child, ref mut done) = *shared.proc_info.lock()?;
-            if *done {
-                break;
-            }
-            if let Ok(Some(_status)) = child.try_wait() {
-                *done = true;
-                shared.cond_var.notify_all();
-            }
+/// Asks all the children being watched (and those which will be) to stop.
+/// These are interrupted, and killed when they are still running after their
+/// grace period.
+pub fn shutdown() {
+    SHUTDOWN.store(true, Ordering::SeqCst);
+    if let Ok(active) = ACTIVE.lock() {
+        for run in active.iter().filter_map(Weak::upgrade) {
+            // holding the lock guarantees the watchdog does not miss the news
+            let _exited = run.exited.lock();
+            run.cond_var.notify_all();
         }
-        thread::sleep(Duration::from_millis(500));
     }
-    Ok(())
+}
+/// Tells whether the launcher has been asked to shut down
+pub fn shutdown_requested() -> bool {
+    SHUTDOWN.load(Ordering::SeqCst)
 }
 
-/// Cleanup the potential zombie kids
-fn maybe_cleanup(child: &mut Child) -> Result<(), Error> {
-    if child.try_wait()?.is_none() {
-        let childrens = list_descendants(child.id() as usize)?;
-        for kid in childrens {
-            kill(&kid)?;
+/// Sends the given signal to all the processes of the group led by pid. It is
+/// not an error if the group has no process anymore.
+fn signal_group(pid: u32, signal: libc::c_int) -> Result<(), Error> {
+    // SAFETY: killpg has no memory safety implication, it only signals a group
+    let rc = unsafe { libc::killpg(pid as libc::pid_t, signal) };
+    if rc == 0 {
+        return Ok(());
+    }
+    let error = std::io::Error::last_os_error();
+    if error.raw_os_error() == Some(libc::ESRCH) {
+        Ok(())
+    } else {
+        Err(error.into())
+    }
+}
+
+/// Blocks until the process finishes and signals it through the conditional
+/// var. The process is left in a waitable state so that its exit status can
+/// still be collected.
+fn wait_until_process_is_finished(pid: u32, shared: Arc<Shared>) -> Result<(), Error> {
+    let result = loop {
+        // SAFETY: info is a plain old data structure which waitid fills in
+        let mut info: libc::siginfo_t = unsafe { mem::zeroed() };
+        let rc = unsafe {
+            libc::waitid(
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOWAIT,
+            )
+        };
+        if rc == 0 {
+            break Ok(());
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != ErrorKind::Interrupted {
+            break Err(error.into());
+        }
+    };
+
+    // even if waiting failed, the main thread must not block forever
+    let shared = shared.as_ref();
+    *shared.exited.lock()? = Some(Instant::now());
+    shared.cond_var.notify_all();
+    result
+}
+
+/// Reaps the given child and returns its exit status along with the resources
+/// it consumed (including those of the descendants it waited for)
+fn reap(pid: libc::pid_t) -> Result<(ExitStatus, Usage), Error> {
+    let mut status = 0;
+    // SAFETY: usage is a plain old data structure which wait4 fills in
+    let mut usage: libc::rusage = unsafe { mem::zeroed() };
+    loop {
+        let rc = unsafe { libc::wait4(pid, &mut status, 0, &mut usage) };
+        if rc >= 0 {
+            break;
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != ErrorKind::Interrupted {
+            return Err(error.into());
         }
     }
-    Ok(())
+    let usage = Usage {
+        user_time: duration(usage.ru_utime),
+        system_time: duration(usage.ru_stime),
+        max_rss: usage.ru_maxrss as u64,
+    };
+    Ok((ExitStatus::from_raw(status), usage))
+}
+
+/// Reaps one of the children in the group led by pid (blocking until one of
+/// them terminates). It returns None once no child is left in that group.
+fn reap_group(pid: u32) -> Result<Option<(ExitStatus, Usage)>, Error> {
+    match reap(-(pid as libc::pid_t)) {
+        Ok(reaped) => Ok(Some(reaped)),
+        Err(Error::Io(error)) if error.raw_os_error() == Some(libc::ECHILD) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Converts a timeval into a duration
+fn duration(tv: libc::timeval) -> Duration {
+    Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
 }
@@ -1,13 +1,19 @@
 use std::{
-    f32,
+    convert::TryFrom,
+    f32, fmt,
     fs::File,
     io::{BufRead, BufReader, Lines, Read},
     path::Path,
 };
 
-use anyhow::Error;
+use serde::{Deserialize, Serialize};
 
-use crate::matrix::Matrix;
+use crate::{
+    errors::Error,
+    matrix::Matrix,
+    parsing::{tokens, Cursor},
+    problem::Problem,
+};
 
 /// This structure represents the TSP with time window instane.
 #[derive(Clone)]
@@ -62,6 +68,243 @@ time_window = {};",
     }
 }
 
+//-----------------------------------------------------------------------------
+//--- SOLVING -----------------------------------------------------------------
+//-----------------------------------------------------------------------------
+
+/// A tour found by minizinc
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Solution {
+    /// The makespan of the tour (scaled by 10000 just like the instance data)
+    pub makespan: usize,
+    /// The order in which the (0-indexed) nodes are visited
+    #[serde(rename = "perm")]
+    pub permutation: Vec<usize>,
+}
+
+impl Problem for TSPTW {
+    type Solution = Solution;
+
+    const NAME: &'static str = "tsptw";
+
+    fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        load_tsptw(path)
+    }
+    fn to_minizinc(&self) -> String {
+        self.to_minizinc()
+    }
+    fn model() -> &'static str {
+        include_str!("../tsptw.mzn")
+    }
+    fn objective(solution: &Solution) -> f64 {
+        solution.makespan as f64 / 10000.0
+    }
+    fn format_row(elapsed: f32, solution: &Solution) -> String {
+        let permutation = solution
+            .permutation
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+        format!(
+            "{:>10} | {:>10.2} | {}",
+            unscale(solution.makespan),
+            elapsed,
+            permutation
+        )
+    }
+    fn parse_row(row: &str) -> Option<Solution> {
+        let columns = row.split('|').map(str::trim).collect::<Vec<&str>>();
+        if columns.len() != 3 {
+            return None;
+        }
+        Some(Solution {
+            makespan: scale(columns[0])?,
+            permutation: columns[2]
+                .split_whitespace()
+                .map(|x| x.parse().ok())
+                .collect::<Option<Vec<usize>>>()?,
+        })
+    }
+    fn check(&self, solution: &Solution) -> Vec<String> {
+        let report = solution.check(self);
+        report.violations.iter().map(|v| v.to_string()).collect()
+    }
+}
+
+//-----------------------------------------------------------------------------
+//--- CHECKING ----------------------------------------------------------------
+//-----------------------------------------------------------------------------
+
+/// Something which is wrong with a tour
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The tour does not have exactly one step per node
+    Length { expected: usize, found: usize },
+    /// The tour goes through a node which does not exist
+    UnknownNode(usize),
+    /// The tour visits the same node more than once
+    Revisited(usize),
+    /// The tour does not end at the depot (it ends at the given node)
+    NoReturn(usize),
+    /// The node is reached after its time window has closed
+    Late {
+        node: usize,
+        arrival: usize,
+        latest: usize,
+    },
+    /// The makespan reported with the tour is not the actual one
+    Makespan { reported: usize, actual: usize },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Length { expected, found } => {
+                write!(f, "the tour has {} steps instead of {}", found, expected)
+            }
+            Violation::UnknownNode(node) => write!(f, "node {} does not exist", node),
+            Violation::Revisited(node) => write!(f, "node {} is visited more than once", node),
+            Violation::NoReturn(node) => write!(f, "the tour ends at node {} iso the depot", node),
+            Violation::Late {
+                node,
+                arrival,
+                latest,
+            } => write!(
+                f,
+                "node {} is reached at {} but its time window closes at {}",
+                node,
+                unscale(*arrival),
+                unscale(*latest)
+            ),
+            Violation::Makespan { reported, actual } => write!(
+                f,
+                "the makespan is {} but {} was reported",
+                unscale(*actual),
+                unscale(*reported)
+            ),
+        }
+    }
+}
+
+/// What the checker tells about some tour
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// The actual makespan of the tour (scaled by 10000). This is only known
+    /// when the tour can be simulated (when all its nodes exist).
+    pub makespan: Option<usize>,
+    /// Everything which is wrong with the tour
+    pub violations: Vec<Violation>,
+}
+
+impl Report {
+    /// Tells whether the tour is a valid solution of the instance
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl TSPTW {
+    /// Checks the given tour (a 0-indexed permutation ending at the depot, just
+    /// like the ones printed by the model). The tour is simulated from the
+    /// opening of the depot time window: the salesman waits whenever he
+    /// reaches a node before its time window opens. Just like in the model,
+    /// the return to the depot is not subject to the depot time window.
+    pub fn check(&self, permutation: &[usize]) -> Report {
+        let mut violations = vec![];
+        if permutation.len() != self.nb_nodes {
+            violations.push(Violation::Length {
+                expected: self.nb_nodes,
+                found: permutation.len(),
+            });
+        }
+        let mut visited = vec![false; self.nb_nodes];
+        for &node in permutation {
+            if node >= self.nb_nodes {
+                violations.push(Violation::UnknownNode(node));
+            } else if visited[node] {
+                violations.push(Violation::Revisited(node));
+            } else {
+                visited[node] = true;
+            }
+        }
+        match permutation.last() {
+            Some(&last) if last != 0 => violations.push(Violation::NoReturn(last)),
+            _ => {}
+        }
+        if permutation.iter().any(|&node| node >= self.nb_nodes) {
+            return Report {
+                makespan: None,
+                violations,
+            };
+        }
+
+        let start = self.timewindows.first().map(|tw| tw.earliest).unwrap_or(0);
+        let mut time = start;
+        let mut current = 0;
+        for &node in permutation {
+            time += self.distances[(current, node)];
+            if node != 0 {
+                let window = self.timewindows[node];
+                time = time.max(window.earliest);
+                if time > window.latest {
+                    violations.push(Violation::Late {
+                        node,
+                        arrival: time,
+                        latest: window.latest,
+                    });
+                }
+            }
+            current = node;
+        }
+        time += self.distances[(current, 0)];
+
+        Report {
+            makespan: Some(time - start),
+            violations,
+        }
+    }
+}
+
+impl Solution {
+    /// Checks this solution of the given instance. Besides the validity of
+    /// the tour, this makes sure the reported makespan is the actual one.
+    pub fn check(&self, instance: &TSPTW) -> Report {
+        let mut report = instance.check(&self.permutation);
+        if let Some(actual) = report.makespan {
+            if actual != self.makespan {
+                report.violations.push(Violation::Makespan {
+                    reported: self.makespan,
+                    actual,
+                });
+            }
+        }
+        report
+    }
+}
+
+/// Prints a time (or distance) scaled by 10000 with its original value. The
+/// conversion is exact: no float is involved.
+fn unscale(value: usize) -> String {
+    format!("{}.{:04}", value / 10000, value % 10000)
+}
+/// Parses a time (or distance) printed by unscale back to its scaled value
+fn scale(text: &str) -> Option<usize> {
+    let (int, frac) = match text.split_once('.') {
+        Some((int, frac)) => (int, frac),
+        None => (text, ""),
+    };
+    let digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if int.is_empty() || frac.len() > 4 || !digits(int) || !digits(frac) {
+        return None;
+    }
+    let frac = format!("{:0<4}", frac).parse::<usize>().ok()?;
+    int.parse::<usize>()
+        .ok()?
+        .checked_mul(10000)?
+        .checked_add(frac)
+}
+
 //-----------------------------------------------------------------------------
 //--- UTILITIES ---------------------------------------------------------------
 //-----------------------------------------------------------------------------
@@ -82,75 +325,104 @@ pub struct TimeWindow {
 //--- PARSING -----------------------------------------------------------------
 //-----------------------------------------------------------------------------
 pub fn load_tsptw<P: AsRef<Path>>(path: P) -> Result<TSPTW, Error> {
-    Ok(TSPTW::from(File::open(path)?))
+    let path = path.as_ref();
+    TSPTW::try_from(File::open(path)?).map_err(|e| e.in_file(path))
 }
 
-impl From<File> for TSPTW {
-    fn from(file: File) -> Self {
-        Self::from(BufReader::new(file))
+impl TryFrom<File> for TSPTW {
+    type Error = Error;
+
+    fn try_from(file: File) -> Result<Self, Error> {
+        Self::try_from(BufReader::new(file))
     }
 }
-impl<S: Read> From<BufReader<S>> for TSPTW {
-    fn from(buf: BufReader<S>) -> Self {
-        Self::from(buf.lines())
+impl<S: Read> TryFrom<BufReader<S>> for TSPTW {
+    type Error = Error;
+
+    fn try_from(buf: BufReader<S>) -> Result<Self, Error> {
+        Self::try_from(buf.lines())
     }
 }
-impl<B: BufRead> From<Lines<B>> for TSPTW {
-    fn from(lines: Lines<B>) -> Self {
+impl<B: BufRead> TryFrom<Lines<B>> for TSPTW {
+    type Error = Error;
+
+    fn try_from(lines: Lines<B>) -> Result<Self, Error> {
+        let mut cursor = Cursor::new(lines);
         let mut lc = 0;
         let mut nb_nodes = 0;
-        let mut distances = Matrix::new(nb_nodes as usize, nb_nodes as usize, 0);
+        let mut distances = Matrix::new(nb_nodes, nb_nodes, 0);
         let mut timewindows = vec![];
 
-        for line in lines {
-            let line = line.unwrap();
-            let line = line.trim();
+        while let Some(line) = cursor.next_line()? {
+            let trimmed = line.trim();
 
             // skip comment lines
-            if line.starts_with('#') || line.is_empty() {
+            if trimmed.starts_with('#') || trimmed.is_empty() {
                 continue;
             }
 
+            let mut tokens = tokens(&line);
             // First line is the number of nodes
             if lc == 0 {
-                nb_nodes = line
-                    .split_whitespace()
-                    .next()
-                    .unwrap()
-                    .to_string()
-                    .parse::<usize>()
-                    .unwrap();
-                distances = Matrix::new(nb_nodes as usize, nb_nodes as usize, 0);
+                let token = tokens.next().expect("a non blank line has tokens");
+                nb_nodes = cursor.parse::<usize>(token, "the number of nodes")?;
+                distances = Matrix::new(nb_nodes, nb_nodes, 0);
             }
             // The next 'nb_nodes' lines represent the distances matrix
             else if (1..=nb_nodes).contains(&lc) {
-                let i = (lc - 1) as usize;
-                for (j, distance) in line.split_whitespace().enumerate() {
-                    let distance = distance.to_string().parse::<f32>().unwrap();
+                let i = lc - 1;
+                let mut count = 0;
+                for (j, token) in tokens.enumerate() {
+                    if j >= nb_nodes {
+                        let expected = format!("{} distances", nb_nodes);
+                        return Err(cursor.error(token.0, expected, "an extra distance"));
+                    }
+                    let distance = cursor.parse::<f32>(token, "a distance")?;
                     let distance = (distance * 10000.0) as usize;
                     distances[(i, j)] = distance;
+                    count += 1;
+                }
+                if count < nb_nodes {
+                    let column = 1 + line.trim_end().len();
+                    let expected = format!("{} distances", nb_nodes);
+                    return Err(cursor.error(column, expected, count));
                 }
             }
             // Finally, the last 'nb_nodes' lines impose the time windows constraints
-            else {
-                let mut tokens = line.split_whitespace();
-                let earliest = tokens.next().unwrap().to_string().parse::<f32>().unwrap();
-                let latest = tokens.next().unwrap().to_string().parse::<f32>().unwrap();
+            else if timewindows.len() < nb_nodes {
+                let token = tokens.next().expect("a non blank line has tokens");
+                let earliest = cursor.parse::<f32>(token, "the earliest arrival time")?;
+                let latest = match tokens.next() {
+                    Some(token) => cursor.parse::<f32>(token, "the latest arrival time")?,
+                    None => {
+                        let column = 1 + line.trim_end().len();
+                        return Err(cursor.error(column, "the latest arrival time", "end of line"));
+                    }
+                };
 
                 let earliest = (earliest * 10000.0) as usize;
                 let latest = (latest * 10000.0) as usize;
 
                 let timewind = TimeWindow { earliest, latest };
                 timewindows.push(timewind);
+            } else {
+                return Err(cursor.error(1, "end of file", "an extra time window"));
             }
 
             lc += 1;
         }
 
-        TSPTW {
+        if lc <= nb_nodes {
+            return Err(cursor.eof(format!("{} rows of distances", nb_nodes)));
+        }
+        if timewindows.len() < nb_nodes {
+            return Err(cursor.eof(format!("{} time windows", nb_nodes)));
+        }
+
+        Ok(TSPTW {
             nb_nodes,
             distances,
             timewindows,
-        }
+        })
     }
 }
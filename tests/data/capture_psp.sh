@@ -0,0 +1,8 @@
+#!/bin/sh
+# Regenerates psp.jsonl: the json stream printed by minizinc while it solves
+# psp.txt with the embedded model. Run it from the root of the crate.
+set -e
+cargo run -q -- psp --dry-run target/capture tests/data/psp.txt > /dev/null
+minizinc --json-stream --output-mode json --output-time --intermediate \
+    --solver gecode -r 1 target/capture/psp.mzn target/capture/data/psp.txt.dzn \
+    > tests/data/psp.jsonl
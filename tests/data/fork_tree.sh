@@ -0,0 +1,21 @@
+#!/bin/sh
+# A stand-in for minizinc which forks a chain of nested processes, each of
+# them ignoring SIGINT and recording its pid in a file.
+#
+# usage: fork_tree.sh <depth> <pid file> [orphan]
+# With 'orphan', the script leaves a detached sleeper behind and exits at once.
+trap '' INT
+depth=$1
+pids=$2
+
+if [ "$3" = "orphan" ]; then
+    ( sh -c 'echo $$ >> "$1"; exec sleep 30' sh "$pids" & )
+    while [ ! -s "$pids" ]; do sleep 0.01; done
+    exit 0
+fi
+
+echo $$ >> "$pids"
+if [ "$depth" -gt 0 ]; then
+    sh "$0" $((depth - 1)) "$pids" &
+fi
+sleep 30
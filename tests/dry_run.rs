@@ -0,0 +1,42 @@
+use std::{env, fs};
+
+use mznlaunch::{launcher::Launcher, tsptw::TSPTW};
+
+const INSTANCE: &str = "3
+0 1 2
+1 0 1
+2 1 0
+0 10
+0 10
+0 10
+";
+
+// a single test: it changes the current directory of the whole process
+#[test]
+fn data_is_written_under_the_directory() {
+    let root = env::temp_dir().join(format!("mznlaunch-dry-run-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("bench")).expect("cannot create the bench");
+    fs::write(root.join("inst.tw"), INSTANCE).expect("cannot write the instance");
+    fs::write(root.join("bench/a.tw"), INSTANCE).expect("cannot write the instance");
+    env::set_current_dir(&root).expect("cannot change directory");
+
+    for (instance, dzn) in [
+        ("inst.tw", "out/inst.tw.dzn"),
+        ("./inst.tw", "out/inst.tw.dzn"),
+        ("bench/a.tw", "out/bench/a.tw.dzn"),
+    ] {
+        let command = Launcher::<TSPTW>::new(instance)
+            .dry_run("out")
+            .expect("dry run failed");
+        assert!(
+            command.ends_with(&format!(" out/tsptw.mzn {}", dzn)),
+            "{}",
+            command
+        );
+        let data = fs::read_to_string(root.join(dzn)).expect("no data was written");
+        assert!(data.contains("n = 3;"));
+    }
+    assert!(root.join("out/tsptw.mzn").is_file());
+    let _ = fs::remove_dir_all(&root);
+}
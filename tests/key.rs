@@ -0,0 +1,25 @@
+use std::{env, fs};
+
+use mznlaunch::{launcher::Launcher, results::Trajectory, tsptw::TSPTW};
+
+#[test]
+fn instance_is_identified_by_its_canonical_path() {
+    let root = env::temp_dir().join(format!("mznlaunch-key-{}", std::process::id()));
+    fs::create_dir_all(root.join("bench")).expect("cannot create the bench");
+    fs::write(root.join("bench/a.tw"), "1\n0\n0 10\n").expect("cannot write the instance");
+    let root = fs::canonicalize(&root).expect("no temporary directory");
+
+    let key = Launcher::<TSPTW>::new(root.join("bench/a.tw")).key();
+    for typed in [root.join("./bench/a.tw"), root.join("bench/../bench/a.tw")] {
+        assert_eq!(key, Launcher::<TSPTW>::new(typed).key());
+    }
+    assert_eq!(root.join("bench/a.tw").display().to_string(), key.instance);
+    let trajectory = Trajectory::path("traj", &key);
+    assert!(trajectory.starts_with("traj/bench"), "{:?}", trajectory);
+    let _ = fs::remove_dir_all(&root);
+}
+#[test]
+fn missing_instance_is_kept_as_typed() {
+    let key = Launcher::<TSPTW>::new("no/such/./instance.tw").key();
+    assert_eq!("no/such/./instance.tw", key.instance);
+}
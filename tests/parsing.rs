@@ -0,0 +1,234 @@
+use std::{convert::TryFrom, io::BufReader};
+
+use mznlaunch::{errors::Error, parsing::tokens, psp::Psp, tsptw::TSPTW};
+
+/// A valid psp instance: 5 periods, 2 items
+const PSP: &str = "5
+2
+3
+
+0 2
+3 0
+
+1 1
+
+0 1 0 0 1
+0 0 1 0 1
+";
+/// A valid tsptw instance: 3 nodes
+const TSPTW: &str = "3
+0 1 2
+1 0 1
+2 1 0
+0 10
+0 10
+0 10
+";
+
+/// Where a parse error is located and what it tells
+#[derive(Debug, PartialEq)]
+struct Located {
+    line: usize,
+    column: usize,
+    expected: String,
+    found: String,
+}
+fn located(line: usize, column: usize, expected: &str, found: &str) -> Located {
+    Located {
+        line,
+        column,
+        expected: expected.to_string(),
+        found: found.to_string(),
+    }
+}
+fn parse_error(error: Error) -> Located {
+    match error {
+        Error::Parse {
+            line,
+            column,
+            expected,
+            found,
+            ..
+        } => Located {
+            line,
+            column,
+            expected,
+            found,
+        },
+        e => panic!("expected a parse error, found {:?}", e),
+    }
+}
+fn rejected<T>(result: Result<T, Error>) -> Error {
+    match result {
+        Ok(_) => panic!("the instance should be rejected"),
+        Err(error) => error,
+    }
+}
+fn psp_error(text: &str) -> Located {
+    parse_error(rejected(Psp::try_from(BufReader::new(text.as_bytes()))))
+}
+fn tsptw_error(text: &str) -> Located {
+    parse_error(rejected(TSPTW::try_from(BufReader::new(text.as_bytes()))))
+}
+
+#[test]
+fn tokens_know_their_column() {
+    let tokens = tokens("  12 a\tbc ").collect::<Vec<_>>();
+    assert_eq!(vec![(3, "12"), (6, "a"), (8, "bc")], tokens);
+}
+#[test]
+fn errors_tell_their_file() {
+    let result = TSPTW::try_from(BufReader::new("three\n".as_bytes()));
+    let error = rejected(result).in_file("bench/a.tw");
+    assert_eq!(
+        "bench/a.tw:1:1: expected the number of nodes, found 'three'",
+        error.to_string()
+    );
+}
+
+#[test]
+fn valid_instances_are_parsed() {
+    let psp = Psp::try_from(BufReader::new(PSP.as_bytes())).expect("a valid psp");
+    assert_eq!((5, 2), (psp.horizon, psp.n_items));
+    assert_eq!(vec![1, 1], psp.stocking);
+    assert_eq!(vec![vec![0, 1, 0, 0, 1], vec![0, 0, 1, 0, 1]], psp.demands);
+
+    let tsptw = TSPTW::try_from(BufReader::new(TSPTW.as_bytes())).expect("a valid tsptw");
+    assert_eq!(3, tsptw.nb_nodes);
+    assert_eq!(20_000, tsptw.distances[(0, 2)]);
+    assert_eq!(100_000, tsptw.timewindows[2].latest);
+}
+
+#[test]
+fn psp_truncated_header() {
+    assert_eq!(
+        located(3, 1, "the number of orders", "end of file"),
+        psp_error("5\n2\n")
+    );
+}
+#[test]
+fn psp_truncated_demands() {
+    let truncated = PSP.trim_end().rsplit_once('\n').unwrap().0;
+    assert_eq!(
+        located(11, 1, "2 demands rows", "end of file"),
+        psp_error(truncated)
+    );
+}
+#[test]
+fn psp_empty_header_line() {
+    assert_eq!(
+        located(1, 1, "the horizon", "an empty line"),
+        psp_error(&PSP.replacen("5", "", 1))
+    );
+}
+#[test]
+fn psp_non_numeric_tokens() {
+    assert_eq!(
+        located(1, 1, "the horizon", "'five'"),
+        psp_error(&PSP.replacen("5", "five", 1))
+    );
+    assert_eq!(
+        located(6, 1, "a changeover cost", "'x'"),
+        psp_error(&PSP.replace("3 0", "x 0"))
+    );
+    assert_eq!(
+        located(11, 5, "a demand", "'-1'"),
+        psp_error(&PSP.replace("0 0 1 0 1", "0 0 -1 0 1"))
+    );
+}
+#[test]
+fn psp_wrong_dimensions() {
+    assert_eq!(
+        located(5, 5, "2 changeover costs", "an extra cost"),
+        psp_error(&PSP.replace("0 2", "0 2 7"))
+    );
+    assert_eq!(
+        located(6, 1, "2 changeover rows", "1"),
+        psp_error(&PSP.replace("3 0\n", ""))
+    );
+    assert_eq!(
+        located(7, 1, "a blank line", "an extra changeover row"),
+        psp_error(&PSP.replace("3 0\n", "3 0\n4 0\n"))
+    );
+    assert_eq!(
+        located(8, 1, "2 stocking costs", "3"),
+        psp_error(&PSP.replace("1 1", "1 1 1"))
+    );
+    assert_eq!(
+        located(10, 11, "5 demands", "an extra demand"),
+        psp_error(&PSP.replace("0 1 0 0 1", "0 1 0 0 1 1"))
+    );
+}
+#[test]
+fn psp_short_rows() {
+    assert_eq!(
+        located(5, 2, "2 changeover costs", "1"),
+        psp_error(&PSP.replace("0 2", "0"))
+    );
+    assert_eq!(
+        located(10, 4, "5 demands", "2"),
+        psp_error(&PSP.replace("0 1 0 0 1", "0 1"))
+    );
+}
+
+#[test]
+fn tsptw_truncated_distances() {
+    assert_eq!(
+        located(3, 1, "3 rows of distances", "end of file"),
+        tsptw_error("3\n0 1 2\n")
+    );
+}
+#[test]
+fn tsptw_truncated_time_windows() {
+    let truncated = TSPTW.trim_end().rsplit_once('\n').unwrap().0;
+    assert_eq!(
+        located(7, 1, "3 time windows", "end of file"),
+        tsptw_error(truncated)
+    );
+}
+#[test]
+fn tsptw_missing_latest_arrival_time() {
+    let text = TSPTW.replacen("0 10", "0", 1);
+    assert_eq!(
+        located(5, 2, "the latest arrival time", "end of line"),
+        tsptw_error(&text)
+    );
+}
+#[test]
+fn tsptw_non_numeric_tokens() {
+    assert_eq!(
+        located(1, 1, "the number of nodes", "'three'"),
+        tsptw_error(&TSPTW.replacen("3", "three", 1))
+    );
+    assert_eq!(
+        located(3, 3, "a distance", "'y'"),
+        tsptw_error(&TSPTW.replace("1 0 1", "1 y 1"))
+    );
+    assert_eq!(
+        located(6, 1, "the earliest arrival time", "'soon'"),
+        tsptw_error(&TSPTW.replacen("0 10\n0 10", "0 10\nsoon 10", 1))
+    );
+}
+#[test]
+fn tsptw_comments_count_as_lines() {
+    let text = format!("# a comment\n\n{}", TSPTW.replace("1 0 1", "1 y 1"));
+    assert_eq!(located(5, 3, "a distance", "'y'"), tsptw_error(&text));
+}
+#[test]
+fn tsptw_wrong_dimensions() {
+    assert_eq!(
+        located(2, 7, "3 distances", "an extra distance"),
+        tsptw_error(&TSPTW.replace("0 1 2", "0 1 2 3"))
+    );
+    assert_eq!(
+        located(8, 1, "end of file", "an extra time window"),
+        tsptw_error(&format!("{}0 10\n", TSPTW))
+    );
+}
+#[test]
+fn tsptw_short_rows() {
+    assert_eq!(
+        located(2, 4, "3 distances", "2"),
+        tsptw_error(&TSPTW.replace("0 1 2", "0 1"))
+    );
+}
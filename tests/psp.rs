@@ -0,0 +1,196 @@
+use std::{convert::TryFrom, io::BufReader};
+
+use mznlaunch::{
+    launcher::{Event, Status},
+    problem::Problem,
+    psp::{Plan, Psp, Solution, Violation},
+};
+
+/// The instance solved in the json stream below
+const STREAM_INSTANCE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/psp.txt");
+/// The json stream printed by minizinc (`--json-stream --output-mode json
+/// --output-time --intermediate`) while solving psp.txt with the embedded
+/// model. Run `tests/data/capture_psp.sh` to regenerate it: it was written
+/// where minizinc was not available, with the costs of each plan computed
+/// independently (see `captured_plans_check_out`).
+const STREAM: &str = include_str!("data/psp.jsonl");
+
+fn events() -> Vec<Event<Solution>> {
+    STREAM
+        .lines()
+        .map(|line| Event::from_json(line).expect("valid minizinc output"))
+        .collect()
+}
+fn solutions() -> Vec<(f32, Solution)> {
+    events()
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::Solution { elapsed, solution } => Some((elapsed, solution)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn every_solution_is_reported() {
+    let costs = solutions()
+        .iter()
+        .map(|(_, s)| s.total_cost)
+        .collect::<Vec<_>>();
+    assert_eq!(vec![27, 17, 11], costs);
+}
+#[test]
+fn captured_plans_check_out() {
+    let instance = Psp::load(STREAM_INSTANCE).expect("valid instance");
+    for (_, solution) in solutions() {
+        let report = solution.check(&instance);
+        assert!(report.is_valid(), "{:?}", report.violations);
+    }
+}
+#[test]
+fn cost_breakdown_is_reported() {
+    let (_, solution) = &solutions()[2];
+    assert_eq!(8, solution.stocking_cost);
+    assert_eq!(3, solution.changeover_cost);
+    assert_eq!(
+        solution.total_cost,
+        solution.stocking_cost + solution.changeover_cost
+    );
+}
+#[test]
+fn plan_and_elapsed_time_are_reported() {
+    let (elapsed, solution) = &solutions()[2];
+    assert_eq!(vec![0, 0, 1, 1, 1], solution.plan);
+    assert!((elapsed - 0.073).abs() < 1e-6);
+}
+#[test]
+fn final_status_is_reported() {
+    let status = events().into_iter().find_map(|event| match event {
+        Event::Status { status, .. } => Some(status),
+        _ => None,
+    });
+    assert_eq!(Some(Status::OptimalSolution), status);
+}
+#[test]
+fn total_cost_is_an_exact_integer() {
+    // 16777217 cannot be represented exactly by an f32
+    let json = r#"{"tot_st_cost": 16777000, "tot_co_cost": 217, "total_cost": 16777217, "solution": [1, 0, 1, 0, 0]}"#;
+    let solution = serde_json::from_str::<Solution>(json).expect("a valid solution");
+    assert_eq!(16_777_217, solution.total_cost);
+    assert_eq!(
+        "  16777217 |   16777000 |        217 |       0.15 | 1 0 1 0 0",
+        Psp::format_row(0.153, &solution)
+    );
+}
+
+/// Two items over five periods. Switching from item 0 to item 1 costs 2, the
+/// other way round costs 3. Stocking any unit costs 1 per period.
+const INSTANCE: &str = "5
+2
+2
+
+0 2
+3 0
+
+1 1
+
+0 1 0 0 1
+0 0 1 0 1
+";
+
+fn instance() -> Psp {
+    Psp::try_from(BufReader::new(INSTANCE.as_bytes())).expect("valid instance")
+}
+fn solution(stocking_cost: usize, changeover_cost: usize, total_cost: usize) -> Solution {
+    Solution {
+        total_cost,
+        stocking_cost,
+        changeover_cost,
+        plan: vec![0, 1, -1, 1, 0],
+    }
+}
+
+#[test]
+fn costs_are_recomputed() {
+    // item 0 is stocked during periods 0, 2 and 3, item 1 during 1 and 3
+    let report = instance().check(&Plan(vec![0, 1, 0, 1, -1]));
+    assert!(report.is_valid(), "{:?}", report.violations);
+    assert_eq!(Some(3 + 2), report.stocking_cost);
+    // 0 -> 1 -> 0 -> 1
+    assert_eq!(Some(2 + 3 + 2), report.changeover_cost);
+    assert_eq!(Some(12), report.total_cost());
+}
+#[test]
+fn idle_periods_are_no_changeover() {
+    let report = instance().check(&Plan(vec![0, 1, -1, 1, 0]));
+    assert!(report.is_valid(), "{:?}", report.violations);
+    assert_eq!(Some(1 + 2), report.stocking_cost);
+    // 0 -> 1 (idle) 1 -> 0
+    assert_eq!(Some(2 + 3), report.changeover_cost);
+}
+#[test]
+fn missed_demand_is_reported() {
+    let report = instance().check(&Plan(vec![1, 1, 0, 0, -1]));
+    assert_eq!(
+        vec![Violation::Backlog {
+            item: 0,
+            period: 1,
+            produced: 0,
+            required: 1
+        }],
+        report.violations
+    );
+}
+#[test]
+fn wrong_horizon_is_reported() {
+    let report = instance().check(&Plan(vec![0, 1]));
+    assert_eq!(
+        vec![Violation::Length {
+            expected: 5,
+            found: 2
+        }],
+        report.violations
+    );
+    assert_eq!(None, report.total_cost());
+}
+#[test]
+fn unknown_items_are_reported() {
+    let report = instance().check(&Plan(vec![0, 1, 2, -2, 1]));
+    assert_eq!(
+        vec![
+            Violation::UnknownItem { period: 2, item: 2 },
+            Violation::UnknownItem {
+                period: 3,
+                item: -2
+            }
+        ],
+        report.violations
+    );
+    assert_eq!(None, report.total_cost());
+}
+#[test]
+fn reported_costs_are_checked() {
+    let instance = instance();
+    assert!(solution(3, 5, 8).check(&instance).is_valid());
+    assert_eq!(
+        vec![Violation::StockingCost {
+            reported: 4,
+            actual: 3
+        }],
+        solution(4, 5, 8).check(&instance).violations
+    );
+    assert_eq!(
+        vec![Violation::ChangeoverCost {
+            reported: 6,
+            actual: 5
+        }],
+        solution(3, 6, 8).check(&instance).violations
+    );
+    assert_eq!(
+        vec![Violation::TotalCost {
+            reported: 9,
+            actual: 8
+        }],
+        solution(3, 5, 9).check(&instance).violations
+    );
+}
@@ -0,0 +1,101 @@
+use std::{os::unix::process::ExitStatusExt, process::ExitStatus, time::Duration};
+
+use mznlaunch::{
+    launcher::Status,
+    results::{Ending, Record, RunStatus},
+    timeout::{Outcome, Termination, Usage},
+};
+
+fn exited(code: i32) -> ExitStatus {
+    ExitStatus::from_raw(code << 8)
+}
+fn signalled(signal: i32) -> ExitStatus {
+    ExitStatus::from_raw(signal)
+}
+
+#[test]
+fn minizinc_status_comes_first() {
+    let timed_out = Outcome::TimedOut { killed: false };
+    let cases = [
+        (Status::OptimalSolution, RunStatus::Optimal),
+        (Status::Unsatisfiable, RunStatus::Unsat),
+        (Status::UnsatOrUnbounded, RunStatus::Unsat),
+        (Status::Error, RunStatus::Error),
+    ];
+    for (status, expected) in cases {
+        assert_eq!(expected, RunStatus::new(&timed_out, Some(status), true));
+    }
+}
+#[test]
+fn solution_without_proof_is_satisfied() {
+    let outcomes = [
+        Outcome::Completed(exited(0)),
+        Outcome::TimedOut { killed: true },
+        Outcome::CpuExceeded(signalled(libc::SIGXCPU)),
+        Outcome::Failed(exited(1)),
+    ];
+    for outcome in &outcomes {
+        assert_eq!(RunStatus::Satisfied, RunStatus::new(outcome, None, true));
+        let unknown = Some(Status::Unknown);
+        assert_eq!(RunStatus::Satisfied, RunStatus::new(outcome, unknown, true));
+    }
+}
+#[test]
+fn no_solution_depends_on_the_outcome() {
+    let cases = [
+        (Outcome::TimedOut { killed: false }, RunStatus::Timeout),
+        (
+            Outcome::CpuExceeded(signalled(libc::SIGXCPU)),
+            RunStatus::Timeout,
+        ),
+        (Outcome::Failed(exited(1)), RunStatus::Error),
+        (
+            Outcome::MemoryExceeded(signalled(libc::SIGABRT)),
+            RunStatus::Error,
+        ),
+        (Outcome::Completed(exited(0)), RunStatus::Unknown),
+        (Outcome::Interrupted { killed: true }, RunStatus::Unknown),
+    ];
+    for (outcome, expected) in &cases {
+        assert_eq!(
+            *expected,
+            RunStatus::new(outcome, None, false),
+            "{:?}",
+            outcome
+        );
+    }
+}
+#[test]
+fn status_is_printed_in_capitals() {
+    assert_eq!("TIMEOUT  |", format!("{:<9}|", RunStatus::Timeout));
+}
+
+/// A record written before the run is over (and before records knew about
+/// stderr)
+const RECORD: &str = r#"{"instance": "bench/a.tw", "problem": "tsptw", "model": "tsptw.mzn", "solver": "gecode", "seed": null, "time_limit": 10, "mem_limit": null, "cpu_limit": null, "final": false}"#;
+
+fn termination(outcome: Outcome, stderr_tail: &str) -> Termination {
+    Termination {
+        outcome,
+        wall_time: Duration::from_millis(1500),
+        usage: Usage::default(),
+        stderr_tail: stderr_tail.to_string(),
+    }
+}
+#[test]
+fn stderr_is_kept_whatever_the_outcome() {
+    let mut record = serde_json::from_str::<Record>(RECORD).expect("a valid record");
+    assert_eq!(None, record.stderr);
+    let warning = "Warning: model inconsistency detected\n";
+    record.set_termination(&termination(Outcome::TimedOut { killed: true }, warning));
+    assert_eq!(Some(Ending::TimedOut), record.outcome);
+    assert_eq!(Some(1.5), record.wall_time);
+    assert_eq!(Some(warning), record.stderr.as_deref());
+}
+#[test]
+fn silent_runs_have_no_stderr() {
+    let mut record = serde_json::from_str::<Record>(RECORD).expect("a valid record");
+    record.set_termination(&termination(Outcome::Completed(exited(0)), ""));
+    assert_eq!(Some(Ending::Completed), record.outcome);
+    assert_eq!(None, record.stderr);
+}
@@ -0,0 +1,52 @@
+use mznlaunch::{
+    errors::Error,
+    solvers::{find_solver, SolverInfo},
+};
+
+/// Three solvers in the format printed by `minizinc --solvers-json` (2.8)
+const SOLVERS: &str = include_str!("data/solvers.json");
+
+fn solvers() -> Vec<SolverInfo> {
+    serde_json::from_str(SOLVERS).expect("valid solvers list")
+}
+
+#[test]
+fn default_solver_is_read_from_the_extra_info() {
+    let defaults = solvers()
+        .into_iter()
+        .filter(|s| s.is_default)
+        .map(|s| s.id)
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["org.gecode.gecode"], defaults);
+}
+#[test]
+fn default_solver_is_found_when_none_is_given() {
+    let solvers = solvers();
+    let solver = find_solver(&solvers, None).expect("a default solver");
+    assert_eq!("org.gecode.gecode", solver.id);
+}
+#[test]
+fn solvers_are_found_by_id_suffix_or_tag() {
+    let solvers = solvers();
+    for id in ["org.chuffed.chuffed", "chuffed", "lcg"] {
+        let solver = find_solver(&solvers, Some(id)).expect("a known solver");
+        assert_eq!("org.chuffed.chuffed", solver.id);
+    }
+    assert!(matches!(
+        find_solver(&solvers, Some("cplex")),
+        Err(Error::UnknownSolver(id)) if id == "cplex"
+    ));
+}
+#[test]
+fn unsupported_standard_flags_are_rejected() {
+    let solvers = solvers();
+    let gecode = find_solver(&solvers, None).expect("a default solver");
+    assert!(gecode.check_flags(&["-p", "4", "-r", "7"]).is_ok());
+
+    let chuffed = find_solver(&solvers, Some("chuffed")).expect("a known solver");
+    assert!(chuffed.check_flags(&["-r", "7", "--prop-fifo"]).is_ok());
+    assert!(matches!(
+        chuffed.check_flags(&["-r", "7", "-p", "4"]),
+        Err(Error::Unsupported { solver, flag }) if solver == "org.chuffed.chuffed" && flag == "-p"
+    ));
+}
@@ -0,0 +1,52 @@
+use mznlaunch::{
+    results::RunStatus,
+    stats::{Aggregate, Sample, Summary},
+};
+
+fn sample(seed: u64, objective: Option<f64>, status: RunStatus) -> Sample {
+    Sample {
+        seed: Some(seed),
+        objective,
+        time_to_best: objective.map(|_| seed as f32),
+        status: Some(status),
+    }
+}
+
+#[test]
+fn median_of_odd_count_is_the_middle_value() {
+    let a = Aggregate::of(vec![3.0, 1.0, 2.0]).expect("some values");
+    assert_eq!((1.0, 2.0, 2.0, 3.0), (a.min, a.median, a.mean, a.max));
+}
+#[test]
+fn median_of_even_count_is_the_mean_of_the_middle_values() {
+    let a = Aggregate::of(vec![4.0, 1.0, 3.0, 10.0]).expect("some values");
+    assert_eq!((1.0, 3.5, 4.5, 10.0), (a.min, a.median, a.mean, a.max));
+}
+#[test]
+fn nothing_to_aggregate() {
+    assert!(Aggregate::of(vec![]).is_none());
+}
+#[test]
+fn summary_counts_solved_and_optimal_runs() {
+    let samples = [
+        sample(1, Some(5.0), RunStatus::Optimal),
+        sample(2, Some(7.0), RunStatus::Satisfied),
+        sample(3, None, RunStatus::Timeout),
+    ];
+    let summary = Summary::new("bench/a.tw", &samples);
+    assert_eq!((3, 2, 1), (summary.runs, summary.solved, summary.optimal));
+    assert_eq!(vec![1, 2, 3], summary.seeds);
+    assert_eq!(6.0, summary.objective.expect("some objective").median);
+    assert_eq!(1.5, summary.time_to_best.expect("some time").mean);
+}
+#[test]
+fn summary_without_solved_run() {
+    let samples = [
+        sample(1, None, RunStatus::Timeout),
+        sample(2, None, RunStatus::Error),
+    ];
+    let summary = Summary::new("bench/a.tw", &samples);
+    assert_eq!((2, 0, 0), (summary.runs, summary.solved, summary.optimal));
+    assert!(summary.objective.is_none());
+    assert!(summary.time_to_best.is_none());
+}
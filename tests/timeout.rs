@@ -0,0 +1,156 @@
+use std::{
+    fs,
+    os::unix::process::CommandExt,
+    path::PathBuf,
+    process::{Child, Command},
+    thread,
+    time::{Duration, Instant},
+};
+
+use mznlaunch::timeout::{timeout, Outcome};
+
+/// A stand-in for minizinc which forks deep process trees
+const FORK_TREE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/fork_tree.sh");
+
+/// Returns a fresh file where the stand-in records the pids it forks
+fn pid_file(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("mznlaunch-{}-{}", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+/// Spawns the stand-in in its own process group
+fn spawn(pids: &PathBuf, depth: usize, orphan: bool) -> Child {
+    let mut command = Command::new("sh");
+    command.arg(FORK_TREE).arg(depth.to_string()).arg(pids);
+    if orphan {
+        command.arg("orphan");
+    }
+    command
+        .process_group(0)
+        .spawn()
+        .expect("cannot spawn the stand-in")
+}
+/// Returns the pids recorded by the stand-in
+fn recorded(pids: &PathBuf) -> Vec<u32> {
+    fs::read_to_string(pids)
+        .expect("no pid was recorded")
+        .lines()
+        .map(|line| line.trim().parse().expect("not a pid"))
+        .collect()
+}
+/// Tells whether the given process is still running (zombies are dead)
+fn alive(pid: u32) -> bool {
+    match fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Err(_) => false,
+        Ok(stat) => {
+            let state = stat.rsplit(')').next().unwrap_or("").trim_start();
+            !state.starts_with('Z') && !state.starts_with('X')
+        }
+    }
+}
+/// Waits (a little) for the given processes to die and returns the survivors
+fn survivors(pids: &[u32]) -> Vec<u32> {
+    let start = Instant::now();
+    loop {
+        let alive = pids
+            .iter()
+            .copied()
+            .filter(|p| alive(*p))
+            .collect::<Vec<_>>();
+        if alive.is_empty() || start.elapsed() > Duration::from_secs(2) {
+            return alive;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn whole_tree_is_killed_on_timeout() {
+    let pids = pid_file("tree");
+    let child = spawn(&pids, 8, false);
+    let termination = timeout(
+        child,
+        Duration::from_millis(500),
+        Duration::from_millis(200),
+    )
+    .expect("timeout failed");
+
+    assert!(matches!(
+        termination.outcome,
+        Outcome::TimedOut { killed: true }
+    ));
+    let forked = recorded(&pids);
+    assert_eq!(9, forked.len());
+    assert_eq!(Vec::<u32>::new(), survivors(&forked));
+    let _ = fs::remove_file(&pids);
+}
+#[test]
+fn orphans_are_killed_when_the_child_completes() {
+    let pids = pid_file("orphan");
+    let child = spawn(&pids, 0, true);
+    let termination = timeout(child, Duration::from_secs(10), Duration::from_millis(200))
+        .expect("timeout failed");
+
+    assert!(matches!(termination.outcome, Outcome::Completed(_)));
+    assert!(termination.wall_time < Duration::from_secs(10));
+    let forked = recorded(&pids);
+    assert_eq!(1, forked.len());
+    assert_eq!(Vec::<u32>::new(), survivors(&forked));
+    let _ = fs::remove_file(&pids);
+}
+#[test]
+fn usage_of_killed_descendants_is_accounted_for() {
+    // the shell waits while its (SIGINT ignoring) child burns cpu
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg("trap '' INT; (while :; do :; done) & wait")
+        .process_group(0)
+        .spawn()
+        .expect("cannot spawn the burner");
+    let termination = timeout(
+        child,
+        Duration::from_millis(500),
+        Duration::from_millis(100),
+    )
+    .expect("timeout failed");
+
+    assert!(matches!(
+        termination.outcome,
+        Outcome::TimedOut { killed: true }
+    ));
+    assert!(termination.usage.cpu_time() >= Duration::from_millis(100));
+}
+#[test]
+fn child_stopping_on_sigint_is_not_killed() {
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg("sleep 30")
+        .process_group(0)
+        .spawn()
+        .expect("cannot spawn the sleeper");
+    let time_limit = Duration::from_millis(300);
+    let grace = Duration::from_secs(2);
+    let termination = timeout(child, time_limit, grace).expect("timeout failed");
+
+    assert!(matches!(
+        termination.outcome,
+        Outcome::TimedOut { killed: false }
+    ));
+    assert!(termination.wall_time >= time_limit);
+    assert!(termination.wall_time < time_limit + grace);
+}
+#[test]
+fn wall_time_is_accurate() {
+    let child = Command::new("sleep")
+        .arg("0.1")
+        .process_group(0)
+        .spawn()
+        .expect("cannot spawn the sleeper");
+    let termination = timeout(child, Duration::from_secs(10), Duration::from_millis(200))
+        .expect("timeout failed");
+
+    assert!(matches!(termination.outcome, Outcome::Completed(_)));
+    let wall_time = termination.wall_time;
+    assert!(wall_time >= Duration::from_millis(90), "{:?}", wall_time);
+    assert!(wall_time < Duration::from_millis(150), "{:?}", wall_time);
+}
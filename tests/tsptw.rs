@@ -0,0 +1,115 @@
+use std::{convert::TryFrom, io::BufReader};
+
+use mznlaunch::{
+    problem::Problem,
+    tsptw::{Solution, Violation, TSPTW},
+};
+
+/// Three nodes on a line, one unit apart. Node 2 opens late, node 1 closes
+/// early: hence the tour 1 2 0 waits at node 2 and the tour 2 1 0 is late.
+const INSTANCE: &str = "3
+0 1 2
+1 0 1
+2 1 0
+0 100
+0 5.5
+5 6
+";
+
+fn instance() -> TSPTW {
+    TSPTW::try_from(BufReader::new(INSTANCE.as_bytes())).expect("valid instance")
+}
+fn solution(makespan: usize, permutation: &[usize]) -> Solution {
+    Solution {
+        makespan,
+        permutation: permutation.to_vec(),
+    }
+}
+
+#[test]
+fn makespan_includes_waiting_and_return() {
+    let report = instance().check(&[1, 2, 0]);
+    assert!(report.is_valid(), "{:?}", report.violations);
+    assert_eq!(Some(70_000), report.makespan);
+}
+#[test]
+fn late_arrival_is_reported() {
+    let report = instance().check(&[2, 1, 0]);
+    assert_eq!(
+        vec![Violation::Late {
+            node: 1,
+            arrival: 60_000,
+            latest: 55_000
+        }],
+        report.violations
+    );
+    assert_eq!(Some(70_000), report.makespan);
+}
+#[test]
+fn tour_must_visit_every_node_once() {
+    let report = instance().check(&[1, 1, 0]);
+    assert_eq!(vec![Violation::Revisited(1)], report.violations);
+
+    let report = instance().check(&[1, 0]);
+    assert_eq!(
+        vec![Violation::Length {
+            expected: 3,
+            found: 2
+        }],
+        report.violations
+    );
+}
+#[test]
+fn tour_must_end_at_the_depot() {
+    let report = instance().check(&[0, 1, 2]);
+    assert_eq!(vec![Violation::NoReturn(2)], report.violations);
+}
+#[test]
+fn unknown_node_cannot_be_simulated() {
+    let report = instance().check(&[1, 7, 0]);
+    assert_eq!(vec![Violation::UnknownNode(7)], report.violations);
+    assert_eq!(None, report.makespan);
+}
+#[test]
+fn wrong_makespan_is_reported() {
+    let report = solution(60_000, &[1, 2, 0]).check(&instance());
+    assert_eq!(
+        vec![Violation::Makespan {
+            reported: 60_000,
+            actual: 70_000
+        }],
+        report.violations
+    );
+    assert_eq!(
+        "the makespan is 7.0000 but 6.0000 was reported",
+        report.violations[0].to_string()
+    );
+}
+#[test]
+fn row_shows_the_exact_makespan() {
+    // 4500.0002 cannot be represented exactly by an f32
+    let row = TSPTW::format_row(1.5, &solution(45_000_002, &[2, 1, 0]));
+    assert_eq!(" 4500.0002 |       1.50 | 2 1 0", row);
+}
+#[test]
+fn row_round_trips() {
+    for makespan in [0, 7, 70_000, 45_000_002, 123_456_789_012] {
+        let expected = solution(makespan, &[2, 1, 0]);
+        let row = TSPTW::format_row(0.25, &expected);
+        let parsed = TSPTW::parse_row(&row).expect("a valid row");
+        assert_eq!(expected.makespan, parsed.makespan, "{}", row);
+        assert_eq!(expected.permutation, parsed.permutation);
+    }
+}
+#[test]
+fn malformed_rows_are_rejected() {
+    for row in [
+        "4500.00021 | 1.50 | 2 1 0",
+        "-1.0 | 1.50 | 2 1 0",
+        "abc | 1.50 | 2 1 0",
+        "1.5 | 1.50 | 2 x 0",
+        "1.5 | 2 1 0",
+    ] {
+        assert!(TSPTW::parse_row(row).is_none(), "{}", row);
+    }
+}